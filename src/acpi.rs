@@ -0,0 +1,313 @@
+use plist::{Dictionary, Value};
+
+use std::error::Error;
+use std::fs;
+use std::io::{Stdout, Write};
+use std::path::Path;
+
+/// where Linux exposes the firmware ACPI tables
+pub const SYSFS_TABLES: &str = "/sys/firmware/acpi/tables";
+
+/// folder inside the INPUT folder that tables are dumped to
+pub const DUMP_DIR: &str = "ACPI_dump";
+
+/// size of the standard header at the start of every ACPI table
+pub const HEADER_SIZE: usize = 36;
+
+/// the standard ACPI System Description Table header
+#[derive(Debug, Default)]
+pub struct Header {
+    pub signature: String, // DSDT, SSDT, etc.
+    pub length: u32,       // length of the whole table including header
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: String,
+    pub oem_table_id: String,
+    pub raw_oem_table_id: Vec<u8>, // padded id as stored in the table
+    pub oem_revision: u32,
+    pub creator_id: String,
+    pub creator_revision: u32,
+    pub expected_checksum: u8, // checksum that makes all bytes of the table sum to zero
+}
+
+impl Header {
+    pub fn checksum_valid(&self) -> bool {
+        self.checksum == self.expected_checksum
+    }
+
+    /// value to use for an empty ACPI > Add Comment, the table ID or the signature
+    /// if the table has no ID
+    pub fn comment(&self) -> String {
+        if self.oem_table_id.is_empty() {
+            self.signature.to_owned()
+        } else {
+            self.oem_table_id.to_owned()
+        }
+    }
+}
+
+/// read the ACPI table at `path` and parse its header
+pub fn inspect(path: &Path) -> Result<Header, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    parse_header(&data)
+}
+
+/// parse the header of an ACPI table and validate its length and checksum
+/// returns an error if `data` is not an ACPI table
+pub fn parse_header(data: &[u8]) -> Result<Header, String> {
+    if data.len() < HEADER_SIZE {
+        return Err(format!(
+            "file is {} bytes, too small to be an ACPI table",
+            data.len()
+        ));
+    }
+    let signature = &data[0..4];
+    if !signature
+        .iter()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || *c == b'_')
+    {
+        return Err("no ACPI table signature found, not an AML file".to_string());
+    }
+    let length = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    if (length as usize) < HEADER_SIZE {
+        return Err(format!(
+            "table length {} is less than the header size",
+            length
+        ));
+    }
+    if length as usize > data.len() {
+        return Err(format!(
+            "table length {} is larger than the file size {}",
+            length,
+            data.len()
+        ));
+    }
+    let sum = data[..length as usize]
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 9)
+        .fold(0u8, |sum, (_, b)| sum.wrapping_add(*b));
+    Ok(Header {
+        signature: ascii(signature),
+        length,
+        revision: data[8],
+        checksum: data[9],
+        oem_id: ascii(&data[10..16]),
+        oem_table_id: ascii(&data[16..24]),
+        raw_oem_table_id: data[16..24].to_vec(),
+        oem_revision: u32::from_le_bytes(data[24..28].try_into().unwrap()),
+        creator_id: ascii(&data[28..32]),
+        creator_revision: u32::from_le_bytes(data[32..36].try_into().unwrap()),
+        expected_checksum: 0u8.wrapping_sub(sum),
+    })
+}
+
+/// copy the ACPI tables from `source`, laid out like /sys/firmware/acpi/tables, into
+/// `dest` as .aml files and write a summary of their headers to `dest`/summary.txt
+/// tables loaded at runtime from the `dynamic` folder get a `dynamic_` prefix,
+/// anything that isn't an ACPI table is skipped, returns the number of tables saved
+/// an existing `dest` is replaced, it can't be or hold `source`
+pub fn dump_tables(
+    source: &Path,
+    dest: &Path,
+    stdout: &mut Stdout,
+) -> Result<usize, Box<dyn Error>> {
+    if let (Ok(src), Ok(dst)) = (source.canonicalize(), dest.canonicalize()) {
+        if src.starts_with(&dst) {
+            return Err(
+                format!("{:?} holds the tables being dumped, not replacing it", dest).into(),
+            );
+        }
+    }
+    let mut tables = vec![];
+    for (dir, prefix) in [
+        (source.to_path_buf(), ""),
+        (source.join("dynamic"), "dynamic_"),
+    ] {
+        if !dir.is_dir() {
+            continue;
+        }
+        let mut files: Vec<_> = fs::read_dir(&dir)?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect();
+        files.sort();
+        for file in files {
+            let mut name = prefix.to_string();
+            name.push_str(&file.file_name().unwrap().to_string_lossy());
+            tables.push((file, name));
+        }
+    }
+    if tables.is_empty() {
+        return Err(format!("no tables found in {:?}", source).into());
+    }
+
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::create_dir_all(dest)?;
+    let mut summary = String::new();
+    let mut count = 0;
+    for (file, name) in tables {
+        let data = match fs::read(&file) {
+            Ok(d) => d,
+            Err(e) => {
+                write!(
+                    stdout,
+                    "\x1b[31mERROR:\x1b[0m reading {:?}: {}\r\n",
+                    file, e
+                )?;
+                continue;
+            }
+        };
+        let header = match parse_header(&data) {
+            Ok(h) => h,
+            Err(e) => {
+                write!(stdout, "\x1b[33mSkipping\x1b[0m {}: {}\r\n", name, e)?;
+                continue;
+            }
+        };
+        let mut aml = name.to_owned();
+        if !aml.to_lowercase().ends_with(".aml") {
+            aml.push_str(".aml");
+        }
+        fs::write(dest.join(&aml), &data)?;
+        let line =
+            format!(
+            "{:<20} {} length {:<7} revision {} OEM {:<6} table ID {:<8} creator {} checksum {}",
+            aml,
+            header.signature,
+            header.length,
+            header.revision,
+            header.oem_id,
+            header.oem_table_id,
+            header.creator_id,
+            if header.checksum_valid() { "valid" } else { "INVALID" },
+        );
+        write!(stdout, "{}\r\n", line)?;
+        summary.push_str(&line);
+        summary.push('\n');
+        count += 1;
+    }
+    fs::write(dest.join("summary.txt"), summary)?;
+    Ok(count)
+}
+
+/// fill an ACPI > Delete entry so it matches the table with this `header`
+pub fn fill_delete_entry(entry: &mut Dictionary, header: &Header) {
+    entry.insert(
+        "TableSignature".to_string(),
+        Value::Data(header.signature.as_bytes().to_vec()),
+    );
+    entry.insert(
+        "OemTableId".to_string(),
+        Value::Data(header.raw_oem_table_id.to_owned()),
+    );
+    entry.insert(
+        "TableLength".to_string(),
+        Value::Integer(plist::Integer::from(header.length)),
+    );
+    entry.insert(
+        "Comment".to_string(),
+        Value::String(format!(
+            "Delete {} {}",
+            header.signature, header.oem_table_id
+        )),
+    );
+    entry.insert("All".to_string(), Value::Boolean(false));
+    entry.insert("Enabled".to_string(), Value::Boolean(true));
+}
+
+/// header fields are space or zero padded ascii
+fn ascii(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches([' ', '\0'])
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a 40 byte SSDT with a valid checksum
+    fn table() -> Vec<u8> {
+        let mut data =
+            b"SSDT\x28\0\0\0\x02\0ACPIDMCpuPlug\0\x01\0\0\0INTL\x20\x10\x20\x20\xa0\x01\x02\x03"
+                .to_vec();
+        let sum = data.iter().fold(0u8, |s, b| s.wrapping_add(*b));
+        data[9] = 0u8.wrapping_sub(sum);
+        data
+    }
+
+    #[test]
+    fn parses_header() {
+        let header = parse_header(&table()).unwrap();
+        assert_eq!(header.signature, "SSDT");
+        assert_eq!(header.length, 40);
+        assert_eq!(header.revision, 2);
+        assert_eq!(header.oem_id, "ACPIDM");
+        assert_eq!(header.oem_table_id, "CpuPlug");
+        assert_eq!(header.raw_oem_table_id, b"CpuPlug\0");
+        assert_eq!(header.oem_revision, 1);
+        assert_eq!(header.creator_id, "INTL");
+        assert_eq!(header.creator_revision, 0x20201020);
+        assert_eq!(header.comment(), "CpuPlug");
+        assert!(header.checksum_valid());
+    }
+
+    #[test]
+    fn finds_bad_checksum() {
+        let mut data = table();
+        data[39] ^= 0xff;
+        let header = parse_header(&data).unwrap();
+        assert!(!header.checksum_valid());
+        data[9] = header.expected_checksum;
+        assert!(parse_header(&data).unwrap().checksum_valid());
+    }
+
+    #[test]
+    fn rejects_non_tables() {
+        assert!(parse_header(&table()[..20]).is_err());
+        let mut data = table();
+        data[0] = b'x';
+        assert!(parse_header(&data).is_err());
+        let mut data = table();
+        data[4] = 41;
+        assert!(parse_header(&data).is_err());
+        data[4] = 20;
+        assert!(parse_header(&data).is_err());
+    }
+
+    #[test]
+    fn dumps_tables_without_touching_source() {
+        let dir = std::env::temp_dir().join(format!("octool_acpi_{}", std::process::id()));
+        let source = dir.join("tables");
+        fs::create_dir_all(source.join("dynamic")).unwrap();
+        fs::write(source.join("SSDT1"), table()).unwrap();
+        fs::write(source.join("dynamic/SSDT2"), table()).unwrap();
+        fs::write(source.join("junk"), b"not a table").unwrap();
+        let mut stdout = std::io::stdout();
+
+        let dest = dir.join("dump");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("old.aml"), b"old").unwrap();
+        assert_eq!(dump_tables(&source, &dest, &mut stdout).unwrap(), 2);
+        assert!(dest.join("SSDT1.aml").exists());
+        assert!(dest.join("dynamic_SSDT2.aml").exists());
+        assert!(!dest.join("old.aml").exists());
+        assert!(dest.join("summary.txt").exists());
+
+        assert!(dump_tables(&source, &source, &mut stdout).is_err());
+        assert!(dump_tables(&source, &dir, &mut stdout).is_err());
+        assert!(source.join("SSDT1").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn comment_falls_back_to_signature() {
+        let mut data = table();
+        data[16..24].copy_from_slice(b"        ");
+        assert_eq!(parse_header(&data).unwrap().comment(), "SSDT");
+    }
+}
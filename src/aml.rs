@@ -0,0 +1,605 @@
+use crate::acpi;
+use crate::init::Settings;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const ZERO_OP: u8 = 0x00;
+const ONE_OP: u8 = 0x01;
+const ALIAS_OP: u8 = 0x06;
+const NAME_OP: u8 = 0x08;
+const BYTE_PREFIX: u8 = 0x0a;
+const WORD_PREFIX: u8 = 0x0b;
+const DWORD_PREFIX: u8 = 0x0c;
+const STRING_PREFIX: u8 = 0x0d;
+const QWORD_PREFIX: u8 = 0x0e;
+const SCOPE_OP: u8 = 0x10;
+const BUFFER_OP: u8 = 0x11;
+const PACKAGE_OP: u8 = 0x12;
+const VAR_PACKAGE_OP: u8 = 0x13;
+const METHOD_OP: u8 = 0x14;
+const EXTERNAL_OP: u8 = 0x15;
+const DUAL_NAME_PREFIX: u8 = 0x2e;
+const MULTI_NAME_PREFIX: u8 = 0x2f;
+const EXT_OP_PREFIX: u8 = 0x5b;
+const ROOT_CHAR: u8 = b'\\';
+const PARENT_PREFIX_CHAR: u8 = b'^';
+const IF_OP: u8 = 0xa0;
+const ELSE_OP: u8 = 0xa1;
+const WHILE_OP: u8 = 0xa2;
+const NOOP_OP: u8 = 0xa3;
+const ONES_OP: u8 = 0xff;
+
+// second byte of the extended opcodes
+const MUTEX_OP: u8 = 0x01;
+const EVENT_OP: u8 = 0x02;
+const REVISION_OP: u8 = 0x30;
+const OP_REGION_OP: u8 = 0x80;
+const FIELD_OP: u8 = 0x81;
+const DEVICE_OP: u8 = 0x82;
+const PROCESSOR_OP: u8 = 0x83;
+const POWER_RES_OP: u8 = 0x84;
+const THERMAL_ZONE_OP: u8 = 0x85;
+const INDEX_FIELD_OP: u8 = 0x86;
+const BANK_FIELD_OP: u8 = 0x87;
+
+/// _HID or _CID values of PCI host bridges
+const PCI_ROOT_IDS: &[&str] = &["PNP0A03", "PNP0A08"];
+
+/// a named object declared in a DSDT or SSDT
+#[derive(Debug, Default)]
+pub struct Object {
+    pub path: Vec<String>,  // name segments from the root, e.g. _SB_ PCI0 LPCB EC0_
+    pub kind: &'static str, // Device, Processor, Method, etc.
+    pub hid: Option<String>, // _HID of a Device
+    pub cid: Option<String>, // _CID of a Device
+    pub adr: Option<u64>,   // _ADR of a Device
+    pub uid: Option<u64>,   // _UID of a Device
+}
+
+impl Object {
+    /// ACPI path as it is written in config.plist and by disassemblers, e.g. \_SB.PCI0.LPCB.EC0
+    pub fn path_string(&self) -> String {
+        path_string(&self.path)
+    }
+
+    fn is_pci_root(&self) -> bool {
+        [&self.hid, &self.cid]
+            .iter()
+            .any(|id| matches!(id, Some(id) if PCI_ROOT_IDS.contains(&id.as_str())))
+    }
+}
+
+/// a value given to an object by a Name op
+#[derive(Debug)]
+enum Data {
+    Int(u64),
+    Str(String),
+    Other,
+}
+
+/// a NameString before it is resolved against the current scope
+#[derive(Debug, Default)]
+struct NameString {
+    root: bool,
+    parents: usize,
+    segs: Vec<String>,
+}
+
+/// the objects declared in the DSDT and SSDT tables of the ACPI_dump folder inside
+/// the `input` folder, sorted by path, empty if there is no dump
+pub fn scan_dump(input: &Path) -> Vec<Object> {
+    let mut tables: Vec<_> = match fs::read_dir(input.join(acpi::DUMP_DIR)) {
+        Ok(dir) => dir.filter_map(Result::ok).map(|e| e.path()).collect(),
+        Err(_) => return vec![],
+    };
+    tables.sort();
+    let mut scanner = Scanner::default();
+    for table in tables {
+        if let Ok(data) = fs::read(&table) {
+            scanner.scan_table(&data);
+        }
+    }
+    scanner.finish()
+}
+
+/// values from the ACPI dump to offer for the highlighted field, or for its key
+/// if `edit_key` is true, empty if the field doesn't hold an ACPI or device path
+pub fn edit_values(settings: &Settings, input: &Path, edit_key: bool) -> Vec<String> {
+    let key = &settings.sec_key;
+    if !edit_key && settings.depth == 3 && key[0] == "ACPI" && key[1] == "Patch" && key[3] == "Base"
+    {
+        base_values(&scan_dump(input))
+    } else if edit_key
+        && settings.depth == 2
+        && key[0] == "DeviceProperties"
+        && (key[1] == "Add" || key[1] == "Delete")
+    {
+        device_property_values(&scan_dump(input))
+    } else {
+        vec![]
+    }
+}
+
+/// list of objects to offer when editing an ACPI > Patch Base
+pub fn base_values(objects: &[Object]) -> Vec<String> {
+    objects
+        .iter()
+        .filter(|o| o.kind != "Name")
+        .map(|o| {
+            let mut val = format!("{} --- {}", o.path_string(), o.kind);
+            if let Some(hid) = &o.hid {
+                val.push(' ');
+                val.push_str(hid);
+            }
+            val
+        })
+        .collect()
+}
+
+/// list of PCI device paths to offer when editing a DeviceProperties key
+pub fn device_property_values(objects: &[Object]) -> Vec<String> {
+    let by_path: HashMap<&Vec<String>, &Object> = objects.iter().map(|o| (&o.path, o)).collect();
+    let mut values: Vec<String> = objects
+        .iter()
+        .filter_map(|o| pci_path(&by_path, o).map(|p| format!("{} --- {}", p, o.path_string())))
+        .collect();
+    values.sort();
+    values
+}
+
+/// PciRoot(..)/Pci(..) device path of `obj` built from the _ADR of it and its
+/// parents up to the PCI host bridge, None if it isn't a PCI device
+fn pci_path<'a>(
+    by_path: &HashMap<&Vec<String>, &'a Object>,
+    mut obj: &'a Object,
+) -> Option<String> {
+    let mut nodes = vec![];
+    loop {
+        if obj.kind != "Device" {
+            return None;
+        }
+        if obj.is_pci_root() {
+            break;
+        }
+        let adr = obj.adr?;
+        nodes.push(format!(
+            "Pci(0x{:x},0x{:x})",
+            (adr >> 16) & 0xffff,
+            adr & 0xffff
+        ));
+        let parent = obj.path[..obj.path.len().checked_sub(1)?].to_vec();
+        obj = by_path.get(&parent)?;
+    }
+    if nodes.is_empty() {
+        return None;
+    }
+    nodes.push(format!("PciRoot(0x{:x})", obj.uid.unwrap_or(0)));
+    nodes.reverse();
+    Some(nodes.join("/"))
+}
+
+/// trailing underscores of each segment are left off, as disassemblers do
+fn path_string(segs: &[String]) -> String {
+    let segs: Vec<&str> = segs
+        .iter()
+        .map(|s| {
+            let t = s.trim_end_matches('_');
+            if t.is_empty() {
+                &s[..1]
+            } else {
+                t
+            }
+        })
+        .collect();
+    format!("\\{}", segs.join("."))
+}
+
+/// walks the AML term lists of a table far enough to find the named objects
+/// opcodes it does not understand are skipped by searching for the next
+/// object declaration, which is all that is needed to learn the names
+#[derive(Default)]
+struct Scanner {
+    objects: Vec<Object>,
+    names: Vec<(Vec<String>, String, Data)>, // scope, name, value of Name ops
+}
+
+impl Scanner {
+    fn scan_table(&mut self, data: &[u8]) {
+        let header = match acpi::parse_header(data) {
+            Ok(h) => h,
+            Err(_) => return,
+        };
+        if header.signature != "DSDT" && header.signature != "SSDT" {
+            return;
+        }
+        self.term_list(data, acpi::HEADER_SIZE, header.length as usize, &[]);
+    }
+
+    /// merge the _HID, _CID, _ADR and _UID names into their objects
+    fn finish(mut self) -> Vec<Object> {
+        let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut objects: Vec<Object> = vec![];
+        for obj in self.objects.drain(..) {
+            // the same object can be declared in more than one table
+            if !index.contains_key(&obj.path) {
+                index.insert(obj.path.clone(), objects.len());
+                objects.push(obj);
+            }
+        }
+        for (scope, name, data) in self.names {
+            let obj = match index.get(&scope) {
+                Some(i) => &mut objects[*i],
+                None => continue,
+            };
+            match (name.as_str(), data) {
+                ("_HID", Data::Str(s)) => obj.hid = Some(s),
+                ("_HID", Data::Int(i)) => obj.hid = Some(eisa_id(i as u32)),
+                ("_CID", Data::Str(s)) => obj.cid = Some(s),
+                ("_CID", Data::Int(i)) => obj.cid = Some(eisa_id(i as u32)),
+                ("_ADR", Data::Int(i)) => obj.adr = Some(i),
+                ("_UID", Data::Int(i)) => obj.uid = Some(i),
+                _ => (),
+            }
+        }
+        objects.sort_by(|a, b| a.path.cmp(&b.path));
+        objects
+    }
+
+    fn term_list(&mut self, data: &[u8], mut pos: usize, end: usize, scope: &[String]) {
+        while pos < end {
+            pos = match self.term(data, pos, end, scope) {
+                Some(next) if next > pos => next,
+                _ => self.resync(data, pos + 1, end),
+            };
+        }
+    }
+
+    /// parse the term at `pos`, returns the position after it or None if the
+    /// term is not one the scanner knows how to step over
+    fn term(&mut self, data: &[u8], pos: usize, end: usize, scope: &[String]) -> Option<usize> {
+        match *data.get(pos)? {
+            SCOPE_OP => {
+                let (body, body_end) = pkg(data, pos + 1, end)?;
+                let (name, body) = name_string(data, body)?;
+                let path = resolve(scope, &name)?;
+                self.term_list(data, body, body_end, &path);
+                Some(body_end)
+            }
+            METHOD_OP => {
+                let (body, body_end) = pkg(data, pos + 1, end)?;
+                let (name, _) = name_string(data, body)?;
+                self.add(resolve(scope, &name)?, "Method");
+                Some(body_end)
+            }
+            NAME_OP => {
+                let (name, next) = name_string(data, pos + 1)?;
+                let (value, next) = data_object(data, next, end)?;
+                let mut path = resolve(scope, &name)?;
+                self.add(path.clone(), "Name");
+                let seg = path.pop()?;
+                self.names.push((path, seg, value));
+                Some(next)
+            }
+            ALIAS_OP => {
+                let (_, next) = name_string(data, pos + 1)?;
+                Some(name_string(data, next)?.1)
+            }
+            EXTERNAL_OP => Some(name_string(data, pos + 1)?.1 + 2),
+            IF_OP | WHILE_OP => {
+                // the predicate is stepped over by resyncing on the first declaration
+                let (body, body_end) = pkg(data, pos + 1, end)?;
+                self.term_list(data, body, body_end, scope);
+                Some(body_end)
+            }
+            ELSE_OP => {
+                let (body, body_end) = pkg(data, pos + 1, end)?;
+                self.term_list(data, body, body_end, scope);
+                Some(body_end)
+            }
+            NOOP_OP => Some(pos + 1),
+            EXT_OP_PREFIX => self.ext_term(data, pos + 1, end, scope),
+            _ => None,
+        }
+    }
+
+    fn ext_term(&mut self, data: &[u8], pos: usize, end: usize, scope: &[String]) -> Option<usize> {
+        match *data.get(pos)? {
+            DEVICE_OP | PROCESSOR_OP | THERMAL_ZONE_OP | POWER_RES_OP => {
+                let (body, body_end) = pkg(data, pos + 1, end)?;
+                let (name, mut body) = name_string(data, body)?;
+                let path = resolve(scope, &name)?;
+                let kind = match data[pos] {
+                    DEVICE_OP => "Device",
+                    PROCESSOR_OP => {
+                        body += 6; // ProcID, PblkAddr, PblkLen
+                        "Processor"
+                    }
+                    THERMAL_ZONE_OP => "ThermalZone",
+                    _ => {
+                        body += 3; // SystemLevel, ResourceOrder
+                        "PowerResource"
+                    }
+                };
+                self.add(path.clone(), kind);
+                if body <= body_end {
+                    self.term_list(data, body, body_end, &path);
+                }
+                Some(body_end)
+            }
+            FIELD_OP | INDEX_FIELD_OP | BANK_FIELD_OP => Some(pkg(data, pos + 1, end)?.1),
+            OP_REGION_OP => {
+                let (_, next) = name_string(data, pos + 1)?;
+                let (_, next) = data_object(data, next + 1, end)?;
+                Some(data_object(data, next, end)?.1)
+            }
+            MUTEX_OP => Some(name_string(data, pos + 1)?.1 + 1),
+            EVENT_OP => Some(name_string(data, pos + 1)?.1),
+            _ => None,
+        }
+    }
+
+    /// find the next position in `pos..end` that starts an object declaration
+    fn resync(&self, data: &[u8], mut pos: usize, end: usize) -> usize {
+        while pos < end {
+            let found = match data[pos] {
+                SCOPE_OP | METHOD_OP => pkg(data, pos + 1, end)
+                    .and_then(|(body, _)| name_string(data, body))
+                    .is_some(),
+                NAME_OP => name_string(data, pos + 1)
+                    .and_then(|(_, next)| data_object(data, next, end))
+                    .is_some(),
+                EXT_OP_PREFIX => match data.get(pos + 1) {
+                    Some(&DEVICE_OP) | Some(&PROCESSOR_OP) | Some(&THERMAL_ZONE_OP) => {
+                        pkg(data, pos + 2, end)
+                            .and_then(|(body, _)| name_string(data, body))
+                            .is_some()
+                    }
+                    _ => false,
+                },
+                _ => false,
+            };
+            if found {
+                return pos;
+            }
+            pos += 1;
+        }
+        end
+    }
+
+    fn add(&mut self, path: Vec<String>, kind: &'static str) {
+        self.objects.push(Object {
+            path,
+            kind,
+            ..Default::default()
+        });
+    }
+}
+
+/// read a PkgLength at `pos`, returns the start and end of the package contents
+fn pkg(data: &[u8], pos: usize, end: usize) -> Option<(usize, usize)> {
+    let lead = *data.get(pos)?;
+    let extra = (lead >> 6) as usize;
+    let mut len = if extra == 0 {
+        (lead & 0x3f) as usize
+    } else {
+        (lead & 0x0f) as usize
+    };
+    for i in 0..extra {
+        len |= (*data.get(pos + 1 + i)? as usize) << (4 + 8 * i);
+    }
+    let pkg_end = pos + len;
+    if len < extra + 1 || pkg_end > end {
+        return None;
+    }
+    Some((pos + 1 + extra, pkg_end))
+}
+
+fn name_seg(data: &[u8], pos: usize) -> Option<String> {
+    let seg = data.get(pos..pos + 4)?;
+    let lead_ok = seg[0].is_ascii_uppercase() || seg[0] == b'_';
+    let rest_ok = seg[1..]
+        .iter()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || *c == b'_');
+    if lead_ok && rest_ok {
+        Some(String::from_utf8_lossy(seg).to_string())
+    } else {
+        None
+    }
+}
+
+fn name_string(data: &[u8], mut pos: usize) -> Option<(NameString, usize)> {
+    let mut name = NameString::default();
+    if *data.get(pos)? == ROOT_CHAR {
+        name.root = true;
+        pos += 1;
+    } else {
+        while *data.get(pos)? == PARENT_PREFIX_CHAR {
+            name.parents += 1;
+            pos += 1;
+        }
+    }
+    let count = match *data.get(pos)? {
+        ZERO_OP => return Some((name, pos + 1)),
+        DUAL_NAME_PREFIX => {
+            pos += 1;
+            2
+        }
+        MULTI_NAME_PREFIX => {
+            pos += 2;
+            *data.get(pos - 1)? as usize
+        }
+        _ => 1,
+    };
+    for _ in 0..count {
+        name.segs.push(name_seg(data, pos)?);
+        pos += 4;
+    }
+    Some((name, pos))
+}
+
+/// absolute path of a declared `name` inside `scope`
+fn resolve(scope: &[String], name: &NameString) -> Option<Vec<String>> {
+    let mut path = if name.root {
+        vec![]
+    } else {
+        scope[..scope.len().checked_sub(name.parents)?].to_vec()
+    };
+    path.extend(name.segs.iter().cloned());
+    Some(path)
+}
+
+/// read the DataRefObject at `pos` that follows a Name op, or a TermArg that is
+/// a simple constant or reference, returns the value and the position after it
+fn data_object(data: &[u8], pos: usize, end: usize) -> Option<(Data, usize)> {
+    let int = |len: usize| -> Option<(Data, usize)> {
+        let bytes = data.get(pos + 1..pos + 1 + len)?;
+        let mut val = 0u64;
+        for (i, b) in bytes.iter().enumerate() {
+            val |= (*b as u64) << (8 * i);
+        }
+        Some((Data::Int(val), pos + 1 + len))
+    };
+    match *data.get(pos)? {
+        ZERO_OP => Some((Data::Int(0), pos + 1)),
+        ONE_OP => Some((Data::Int(1), pos + 1)),
+        ONES_OP => Some((Data::Int(u64::MAX), pos + 1)),
+        BYTE_PREFIX => int(1),
+        WORD_PREFIX => int(2),
+        DWORD_PREFIX => int(4),
+        QWORD_PREFIX => int(8),
+        STRING_PREFIX => {
+            let len = data.get(pos + 1..end)?.iter().position(|c| *c == 0)?;
+            let s = String::from_utf8_lossy(&data[pos + 1..pos + 1 + len]).to_string();
+            Some((Data::Str(s), pos + 2 + len))
+        }
+        BUFFER_OP | PACKAGE_OP | VAR_PACKAGE_OP => Some((Data::Other, pkg(data, pos + 1, end)?.1)),
+        EXT_OP_PREFIX if data.get(pos + 1) == Some(&REVISION_OP) => Some((Data::Other, pos + 2)),
+        _ => name_string(data, pos).map(|(_, next)| (Data::Other, next)),
+    }
+}
+
+/// decode a compressed EISA id such as 0x090cd041 into PNP0C09
+fn eisa_id(id: u32) -> String {
+    let b = id.to_le_bytes();
+    let vendor = ((b[0] as u16) << 8) | b[1] as u16;
+    let letter = |shift: u16| (((vendor >> shift) & 0x1f) as u8 + 0x40) as char;
+    format!(
+        "{}{}{}{:02X}{:02X}",
+        letter(10),
+        letter(5),
+        letter(0),
+        b[2],
+        b[3]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scope (\_SB) { Device (PCI0) { Name (_HID, EisaId ("PNP0A08")) Name (_UID, 0)
+    /// Store (5, ...) Device (GFX0) { Name (_ADR, 0x00020000) } Method (_INI) { Noop } } }
+    /// Processor (CPU0, 1, 0x810, 6) {}, the Store is cut short to test resyncing
+    fn dsdt() -> Vec<u8> {
+        let mut body = vec![SCOPE_OP, 0x39];
+        body.extend(b"\\_SB_");
+        body.extend([EXT_OP_PREFIX, DEVICE_OP, 0x31]);
+        body.extend(b"PCI0");
+        body.push(NAME_OP);
+        body.extend(b"_HID");
+        body.extend([DWORD_PREFIX, 0x41, 0xd0, 0x0a, 0x08]);
+        body.push(NAME_OP);
+        body.extend(b"_UID");
+        body.push(ZERO_OP);
+        body.extend([0x70, BYTE_PREFIX, 0x05]);
+        body.extend([EXT_OP_PREFIX, DEVICE_OP, 0x0f]);
+        body.extend(b"GFX0");
+        body.push(NAME_OP);
+        body.extend(b"_ADR");
+        body.extend([DWORD_PREFIX, 0x00, 0x00, 0x02, 0x00]);
+        body.extend([METHOD_OP, 0x07]);
+        body.extend(b"_INI");
+        body.extend([0x00, NOOP_OP]);
+        body.extend([EXT_OP_PREFIX, PROCESSOR_OP, 0x0b]);
+        body.extend(b"CPU0");
+        body.extend([0x01, 0x10, 0x08, 0x00, 0x00, 0x06]);
+
+        let mut data = b"DSDT".to_vec();
+        data.extend(((acpi::HEADER_SIZE + body.len()) as u32).to_le_bytes());
+        data.extend([2, 0]);
+        data.extend(b"OEMID OEMTABLE");
+        data.extend([0; 4]);
+        data.extend(b"INTL");
+        data.extend([0; 4]);
+        data.extend(body);
+        data
+    }
+
+    fn scan(data: &[u8]) -> Vec<Object> {
+        let mut scanner = Scanner::default();
+        scanner.scan_table(data);
+        scanner.finish()
+    }
+
+    #[test]
+    fn finds_objects() {
+        let objects = scan(&dsdt());
+        let paths: Vec<(String, &str)> = objects
+            .iter()
+            .filter(|o| o.kind != "Name")
+            .map(|o| (o.path_string(), o.kind))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("\\CPU0".to_string(), "Processor"),
+                ("\\_SB.PCI0".to_string(), "Device"),
+                ("\\_SB.PCI0.GFX0".to_string(), "Device"),
+                ("\\_SB.PCI0._INI".to_string(), "Method"),
+            ]
+        );
+        let pci0 = objects
+            .iter()
+            .find(|o| o.path_string() == "\\_SB.PCI0")
+            .unwrap();
+        assert_eq!(pci0.hid.as_deref(), Some("PNP0A08"));
+        assert_eq!(pci0.uid, Some(0));
+    }
+
+    #[test]
+    fn builds_pci_paths() {
+        let objects = scan(&dsdt());
+        assert_eq!(
+            device_property_values(&objects),
+            vec!["PciRoot(0x0)/Pci(0x2,0x0) --- \\_SB.PCI0.GFX0"]
+        );
+        assert!(base_values(&objects).contains(&"\\_SB.PCI0 --- Device PNP0A08".to_string()));
+    }
+
+    #[test]
+    fn ignores_other_tables() {
+        let mut data = dsdt();
+        data[..4].copy_from_slice(b"FACP");
+        assert!(scan(&data).is_empty());
+        assert!(scan(&data[..20]).is_empty());
+    }
+
+    #[test]
+    fn decodes_names() {
+        assert_eq!(eisa_id(0x090c_d041), "PNP0C09");
+        assert_eq!(
+            path_string(&["_SB_".to_string(), "EC__".to_string()]),
+            "\\_SB.EC"
+        );
+        assert_eq!(path_string(&["____".to_string()]), "\\_");
+        let (name, next) = name_string(b"^^\x2eAB__CD__", 0).unwrap();
+        assert_eq!((name.parents, name.segs.len(), next), (2, 2, 11));
+        let scope = vec!["_SB_".to_string(), "PCI0".to_string(), "LPCB".to_string()];
+        assert_eq!(resolve(&scope, &name).unwrap(), ["_SB_", "AB__", "CD__"]);
+        assert!(name_string(b"1ABC", 0).is_none());
+        assert_eq!(pkg(&[0x42, 0x04], 0, 0x100), Some((2, 0x42)));
+        assert_eq!(pkg(&[0x42, 0x04], 0, 0x40), None);
+    }
+}
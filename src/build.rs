@@ -1,3 +1,4 @@
+use crate::acpi;
 use crate::init::Settings;
 use crate::res::{self, get_res_path, res_version, status, Resources};
 
@@ -96,6 +97,10 @@ pub fn build_output(
             res_version(settings, &resources, &res);
             match get_res_path(&settings, &resources, &res, &sec, stdout, false) {
                 Some(res) => {
+                    if &sec == "ACPI" && !check_acpi_table(&res, stdout)? {
+                        build_okay = false;
+                        continue;
+                    }
                     from_paths.push(res);
                 }
                 None => {
@@ -331,3 +336,35 @@ fn compute_vault_plist(resources: &Resources, stdout: &mut Stdout) -> Result<(),
     stdout.flush()?;
     Ok(())
 }
+
+/// check the header and checksum of an ACPI table before it is copied to the OUTPUT
+/// returns false if the file is not an ACPI table, a bad checksum is only a warning
+fn check_acpi_table(path: &str, stdout: &mut Stdout) -> Result<bool, Box<dyn Error>> {
+    let path = Path::new(path);
+    if path.is_dir() {
+        return Ok(true);
+    }
+    match acpi::inspect(path) {
+        Ok(header) => {
+            if !header.checksum_valid() {
+                write!(
+                    stdout,
+                    "\x1B[33mWARNING: {:?} has checksum {:#04x}, should be {:#04x}\x1B[0m\r\n",
+                    path.file_name().unwrap(),
+                    header.checksum,
+                    header.expected_checksum
+                )?;
+            }
+            Ok(true)
+        }
+        Err(e) => {
+            write!(
+                stdout,
+                "\x1B[31mERROR: {:?} {}, skipping\x1B[0m\r\n",
+                path.file_name().unwrap(),
+                e
+            )?;
+            Ok(false)
+        }
+    }
+}
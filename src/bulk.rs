@@ -0,0 +1,380 @@
+use crate::edit::{prompt, read_key, section_array};
+use crate::init::Settings;
+use crate::res::Resources;
+
+use crossterm::event::KeyCode;
+use plist::Value;
+
+use std::collections::BTreeSet;
+use std::io::{Stdout, Write};
+
+/// array items marked with 'm' for the bulk actions
+#[derive(Debug, Default)]
+pub struct Marks {
+    section: Vec<usize>, // sec_num of the array holding the marked items
+    len: usize,          // length of that array when marked, marks are dropped if it changes
+    items: BTreeSet<usize>,
+    block: Vec<Value>, // marked items last copied or deleted, for pasting as a block
+}
+
+impl Marks {
+    /// true if item `item` of the array at `section`, `len` items long, is marked
+    pub fn is_marked(&self, section: &[usize], len: usize, item: usize) -> bool {
+        self.section == section && self.len == len && self.items.contains(&item)
+    }
+
+    /// numbers of the marked items in the array holding the highlighted item
+    pub fn items_here(&self, settings: &Settings) -> Vec<usize> {
+        if !settings.inside_an_array
+            || self.section != settings.sec_num[..settings.depth]
+            || self.len != settings.sec_length[settings.depth]
+        {
+            return vec![];
+        }
+        self.items.iter().copied().collect()
+    }
+}
+
+/// mark or unmark the highlighted array item and move down to the next one
+/// marking an item in another array drops the marks in the last one
+pub fn toggle(settings: &mut Settings) {
+    if !settings.inside_an_array {
+        return;
+    }
+    let section = settings.sec_num[..settings.depth].to_vec();
+    let len = settings.sec_length[settings.depth];
+    let item = settings.sec_num[settings.depth];
+    let marks = &mut settings.marks;
+    if marks.section != section || marks.len != len {
+        marks.items.clear();
+        marks.section = section;
+        marks.len = len;
+    }
+    if !marks.items.remove(&item) {
+        marks.items.insert(item);
+    }
+    settings.down();
+}
+
+/// the items '[' and ']' move, the marked items if the highlighted one is marked,
+/// otherwise just the highlighted item
+pub fn moving(settings: &Settings) -> Vec<usize> {
+    let marked = settings.marks.items_here(settings);
+    if marked.contains(&settings.sec_num[settings.depth]) {
+        marked
+    } else {
+        vec![settings.sec_num[settings.depth]]
+    }
+}
+
+/// keep the marks on the same items after edit::move_items put the item numbered
+/// `order[i]` at place i
+pub fn moved(settings: &mut Settings, order: &[usize]) {
+    let marks = &mut settings.marks;
+    if marks.section == settings.sec_num[..settings.depth] {
+        marks.items = (0..order.len())
+            .filter(|i| marks.items.contains(&order[*i]))
+            .collect();
+    }
+}
+
+/// ask for a bulk action and apply it to the marked items in the array holding the
+/// highlighted item, enable, disable, copy, delete, set a field on all of them, paste
+/// the last copied or deleted block, or unmark them
+pub fn bulk(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    if !settings.inside_an_array {
+        write!(
+            stdout,
+            "\r\n\x1b[33mBulk actions work on the items of an array, e.g. Kernel > Add\x1b[0m\x1b[0K\r\n"
+        )
+        .unwrap();
+        return;
+    }
+    let items = settings.marks.items_here(settings);
+    let block = settings.marks.block.len();
+    if items.is_empty() && block == 0 {
+        write!(
+            stdout,
+            "\r\n\x1b[33mNo items are marked, use '\x1b[32mm\x1b[33m' to mark them\x1b[0m\x1b[0K\r\n"
+        )
+        .unwrap();
+        return;
+    }
+    write!(
+        stdout,
+        "\r\n\x1b[2K\x1b[4m{} items marked\x1b[0m\x1b[0K\r\n\x1b[2K",
+        items.len()
+    )
+    .unwrap();
+    if !items.is_empty() {
+        write!(
+            stdout,
+            "'\x1b[32me\x1b[0m' enable, '\x1b[32md\x1b[0m' disable, '\x1b[32mc\x1b[0m' copy, \
+             '\x1b[32mx\x1b[0m' delete, '\x1b[32ms\x1b[0m' set a field, '\x1b[32mu\x1b[0m' unmark  "
+        )
+        .unwrap();
+    }
+    if block > 0 {
+        write!(
+            stdout,
+            "'\x1b[32mp\x1b[0m' paste the {} copied items  ",
+            block
+        )
+        .unwrap();
+    }
+    write!(stdout, "any other key to cancel\x1b[0K\r\n\x1b[2K").unwrap();
+    stdout.flush().unwrap();
+    let action = read_key().unwrap().0;
+    if items.is_empty() && action != KeyCode::Char('p') {
+        return;
+    }
+    let done = match action {
+        KeyCode::Char('e') => enable(settings, resources, &items, true),
+        KeyCode::Char('d') => enable(settings, resources, &items, false),
+        KeyCode::Char('c') => {
+            let a = section_array(settings, &mut resources.config_plist).unwrap();
+            settings.marks.block = items.iter().map(|i| a[*i].clone()).collect();
+            format!("Copied {} items", items.len())
+        }
+        KeyCode::Char('x') => {
+            write!(
+                stdout,
+                "Press '\x1b[32mx\x1b[0m' again to delete the {} marked items, any other key to cancel\x1b[0K\r\n\x1b[2K",
+                items.len()
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+            if read_key().unwrap().0 != KeyCode::Char('x') {
+                return;
+            }
+            delete(settings, resources, &items)
+        }
+        KeyCode::Char('s') => match set_field(settings, resources, &items, stdout) {
+            Some(done) => done,
+            None => return,
+        },
+        KeyCode::Char('p') => paste(settings, resources),
+        KeyCode::Char('u') => {
+            settings.marks.items.clear();
+            "Unmarked all items".to_string()
+        }
+        _ => return,
+    };
+    write!(stdout, "\x1b[32m{}\x1b[0m\x1b[0K\r\n", done).unwrap();
+}
+
+/// set the Enabled key of the `items` to `on`, or for resources listed as plain
+/// strings add or remove the leading '#' the same as space does
+fn enable(settings: &mut Settings, resources: &mut Resources, items: &[usize], on: bool) -> String {
+    let is_resource = settings.is_resource();
+    let a = section_array(settings, &mut resources.config_plist).unwrap();
+    let mut changed = 0;
+    for i in items {
+        match &mut a[*i] {
+            Value::Dictionary(d) => {
+                if let Some(Value::Boolean(b)) = d.get_mut("Enabled") {
+                    changed += usize::from(*b != on);
+                    *b = on;
+                }
+            }
+            Value::String(s) if is_resource => {
+                if on && s.starts_with('#') {
+                    s.remove(0);
+                    changed += 1;
+                } else if !on && !s.starts_with('#') {
+                    s.insert(0, '#');
+                    changed += 1;
+                }
+            }
+            _ => (),
+        }
+    }
+    settings.modified |= changed > 0;
+    format!(
+        "{} {} of {} items",
+        if on { "Enabled" } else { "Disabled" },
+        changed,
+        items.len()
+    )
+}
+
+/// remove the `items` and keep them as the block to paste
+fn delete(settings: &mut Settings, resources: &mut Resources, items: &[usize]) -> String {
+    let a = section_array(settings, &mut resources.config_plist).unwrap();
+    let mut removed = items
+        .iter()
+        .rev()
+        .map(|i| a.remove(*i))
+        .collect::<Vec<Value>>();
+    removed.reverse();
+    let len = a.len();
+    settings.marks.block = removed;
+    settings.marks.items.clear();
+    settings.sec_length[settings.depth] = len;
+    settings.sec_num[settings.depth] = items[0].min(len.saturating_sub(1));
+    settings.modified = true;
+    format!("Deleted {} items, 'B' 'p' pastes them back", items.len())
+}
+
+/// insert the last copied or deleted block at the highlighted item
+fn paste(settings: &mut Settings, resources: &mut Resources) -> String {
+    let at = settings.sec_num[settings.depth];
+    let block = settings.marks.block.clone();
+    let a = section_array(settings, &mut resources.config_plist).unwrap();
+    let at = at.min(a.len());
+    for (i, item) in block.iter().enumerate() {
+        a.insert(at + i, item.clone());
+    }
+    settings.sec_length[settings.depth] = a.len();
+    settings.modified = true;
+    format!("Pasted {} items", block.len())
+}
+
+/// ask for a key and a value and set it on every marked dictionary, the value is
+/// read as the type the key already has in the first marked item that has it,
+/// e.g. MinKernel 20.0.0 or Arch x86_64, new keys are added as strings
+fn set_field(
+    settings: &mut Settings,
+    resources: &mut Resources,
+    items: &[usize],
+    stdout: &mut Stdout,
+) -> Option<String> {
+    let mut key = String::new();
+    if !prompt("Key to set on the marked items:", &mut key, stdout) || key.is_empty() {
+        return None;
+    }
+    let mut text = String::new();
+    if !prompt(&format!("Value for {}:", key), &mut text, stdout) {
+        return None;
+    }
+    let a = section_array(settings, &mut resources.config_plist).unwrap();
+    let like = items
+        .iter()
+        .find_map(|i| a[*i].as_dictionary().and_then(|d| d.get(&key)));
+    let val = match typed(&text, like) {
+        Some(v) => v,
+        None => {
+            return Some(format!(
+                "\x1b[31mERROR:\x1b[0m {} is not a valid value for {}",
+                text, key
+            ))
+        }
+    };
+    let mut set = 0;
+    for i in items {
+        if let Some(d) = a[*i].as_dictionary_mut() {
+            d.insert(key.to_owned(), val.clone());
+            set += 1;
+        }
+    }
+    settings.modified |= set > 0;
+    Some(format!("Set {} on {} of {} items", key, set, items.len()))
+}
+
+/// `text` as a value of the same type as `like`, a string if there is no `like`
+fn typed(text: &str, like: Option<&Value>) -> Option<Value> {
+    match like {
+        Some(Value::Boolean(_)) => text.parse::<bool>().ok().map(Value::Boolean),
+        Some(Value::Integer(_)) => text.parse::<i64>().ok().map(|i| Value::Integer(i.into())),
+        Some(Value::Data(_)) => hex::decode(text.replace(' ', "")).ok().map(Value::Data),
+        Some(Value::String(_)) | None => Some(Value::String(text.to_owned())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// settings with the highlight on item `item` of a `len` item Kernel > Add
+    fn in_kernel_add(item: usize, len: usize) -> Settings {
+        Settings {
+            depth: 2,
+            sec_key: vec!["Kernel".to_string(), "Add".to_string(), item.to_string()],
+            sec_num: vec![0, 0, item],
+            sec_length: vec![1, 1, len],
+            inside_an_array: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn marks_and_moves_down() {
+        let mut settings = in_kernel_add(1, 5);
+        toggle(&mut settings);
+        toggle(&mut settings);
+        assert_eq!(settings.sec_num[2], 3);
+        toggle(&mut settings);
+        assert_eq!(settings.marks.items_here(&settings), vec![1, 2, 3]);
+        assert!(settings.marks.is_marked(&[0, 0], 5, 2));
+        assert!(!settings.marks.is_marked(&[0, 0], 6, 2));
+
+        // toggling a marked item unmarks it
+        settings.sec_num[2] = 2;
+        toggle(&mut settings);
+        assert_eq!(settings.marks.items_here(&settings), vec![1, 3]);
+    }
+
+    #[test]
+    fn marks_are_dropped_elsewhere() {
+        let mut settings = in_kernel_add(1, 5);
+        toggle(&mut settings);
+        settings.sec_length[2] = 4;
+        assert!(settings.marks.items_here(&settings).is_empty());
+        toggle(&mut settings);
+        assert_eq!(settings.marks.items_here(&settings), vec![2]);
+        settings.sec_num[1] = 1;
+        assert!(settings.marks.items_here(&settings).is_empty());
+        settings.inside_an_array = false;
+        toggle(&mut settings);
+        assert_eq!(settings.marks.items.len(), 1);
+    }
+
+    #[test]
+    fn moves_marked_or_highlighted() {
+        let mut settings = in_kernel_add(1, 5);
+        toggle(&mut settings);
+        settings.sec_num[2] = 3;
+        toggle(&mut settings);
+        settings.sec_num[2] = 3;
+        assert_eq!(moving(&settings), vec![1, 3]);
+        settings.sec_num[2] = 2;
+        assert_eq!(moving(&settings), vec![2]);
+    }
+
+    #[test]
+    fn marks_follow_moved_items() {
+        let mut settings = in_kernel_add(1, 5);
+        toggle(&mut settings);
+        settings.sec_num[2] = 3;
+        toggle(&mut settings);
+        // items 1 and 3 moved up a place
+        moved(&mut settings, &[1, 0, 3, 2, 4]);
+        assert_eq!(settings.marks.items_here(&settings), vec![0, 2]);
+        // in another array the marks stay as they are
+        settings.sec_num[1] = 1;
+        moved(&mut settings, &[1, 0, 2, 3, 4]);
+        settings.sec_num[1] = 0;
+        assert_eq!(settings.marks.items_here(&settings), vec![0, 2]);
+    }
+
+    #[test]
+    fn reads_typed_values() {
+        let t = |text: &str, like: Value| typed(text, Some(&like));
+        assert_eq!(t("true", Value::Boolean(false)), Some(Value::Boolean(true)));
+        assert_eq!(t("yes", Value::Boolean(false)), None);
+        assert_eq!(
+            t("20", Value::Integer(0.into())),
+            Some(Value::Integer(20.into()))
+        );
+        assert_eq!(
+            t("0A 0b", Value::Data(vec![])),
+            Some(Value::Data(vec![0x0a, 0x0b]))
+        );
+        assert_eq!(t("x", Value::Array(vec![])), None);
+        assert_eq!(
+            typed("20.0.0", None),
+            Some(Value::String("20.0.0".to_string()))
+        );
+    }
+}
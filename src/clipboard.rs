@@ -0,0 +1,148 @@
+use crate::edit::{self, read_key};
+use crate::init::Settings;
+use crate::res::Resources;
+
+use crossterm::event::KeyCode;
+use plist::Value;
+
+use std::collections::BTreeMap;
+use std::io::{Stdout, Write};
+
+/// number of cut or copied items kept in the clipboard history
+const MAX_RECENT: usize = 20;
+
+/// a cut or copied item and where it came from
+#[derive(Clone, Debug)]
+pub struct Clip {
+    key: String,
+    item: Value,
+    path: String, // e.g. Kernel->Add->Lilu.kext
+}
+
+/// vi style named registers and the history of cut or copied items
+#[derive(Debug, Default)]
+pub struct Clipboard {
+    pub pending: Option<char>, // register named with " for the next cut, copy or paste
+    registers: BTreeMap<char, Clip>,
+    recent: Vec<Clip>, // newest first
+}
+
+/// ask for the register the next cut, copy or paste uses, a-z, or '"' to show the
+/// clipboard history instead
+pub fn select_register(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    write!(
+        stdout,
+        "\r\n\x1b[2KRegister \x1b[32ma\x1b[0m-\x1b[32mz\x1b[0m for the next cut, copy or paste, \
+         '\x1b[32m\"\x1b[0m' to show the clipboard history, any other key to cancel\x1b[0K\r\n\x1b[2K"
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    match read_key().unwrap().0 {
+        KeyCode::Char(c) if c.is_ascii_lowercase() => settings.clipboard.pending = Some(c),
+        KeyCode::Char('"') => show_history(settings, resources, stdout),
+        _ => (),
+    }
+}
+
+/// keep the item just cut or copied into settings.held_item in the clipboard
+/// history and in the pending register, if one was named
+pub fn store(settings: &mut Settings) {
+    let item = match &settings.held_item {
+        Some(i) => i.to_owned(),
+        None => return,
+    };
+    let clip = Clip {
+        key: settings.held_key.to_owned(),
+        item,
+        path: settings.sec_key[..=settings.depth].join("->"),
+    };
+    if let Some(c) = settings.clipboard.pending.take() {
+        settings.clipboard.registers.insert(c, clip.clone());
+    }
+    settings.clipboard.recent.insert(0, clip);
+    settings.clipboard.recent.truncate(MAX_RECENT);
+}
+
+/// put the pending register, if one was named, in settings.held_item ready to be
+/// pasted, returns false if the named register is empty
+pub fn load(settings: &mut Settings) -> bool {
+    let c = match settings.clipboard.pending.take() {
+        Some(c) => c,
+        None => return true,
+    };
+    match settings.clipboard.registers.get(&c) {
+        Some(clip) => {
+            settings.held_key = clip.key.to_owned();
+            settings.held_item = Some(clip.item.to_owned());
+            true
+        }
+        None => false,
+    }
+}
+
+/// list the named registers and recently cut or copied items with the key paths
+/// they came from, the one selected is pasted at the highlighted location
+fn show_history(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    let mut clips = vec![];
+    for (c, clip) in &settings.clipboard.registers {
+        clips.push((format!("\"{}", c), clip.to_owned()));
+    }
+    for (i, clip) in settings.clipboard.recent.iter().enumerate() {
+        clips.push((format!("{:>2}", i + 1), clip.to_owned()));
+    }
+    if clips.is_empty() {
+        write!(
+            stdout,
+            "\x1b[33mNothing has been cut or copied yet\x1b[0m\x1b[0K\r\n"
+        )
+        .unwrap();
+        return;
+    }
+    write!(
+        stdout,
+        "\x1b[4mClipboard\x1b[0m - up/down to select, Enter to paste at the highlighted location, Esc to cancel\x1b[0K\r\n\x1b[2K\x1B7"
+    )
+    .unwrap();
+    let mut selection = 0;
+    loop {
+        write!(stdout, "\x1B8").unwrap();
+        for (i, (name, clip)) in clips.iter().enumerate() {
+            write!(
+                stdout,
+                "{}{} {:<50} {}\x1b[0m\x1b[0K\r\n\x1B[2K",
+                if i == selection { "\x1B[7m" } else { "" },
+                name,
+                clip.path,
+                kind(&clip.item)
+            )
+            .unwrap();
+        }
+        stdout.flush().unwrap();
+        match read_key().unwrap().0 {
+            KeyCode::Up if selection > 0 => selection -= 1,
+            KeyCode::Down if selection < clips.len() - 1 => selection += 1,
+            KeyCode::Enter => break,
+            KeyCode::Esc => return,
+            _ => (),
+        }
+    }
+    let clip = &clips[selection].1;
+    settings.held_key = clip.key.to_owned();
+    settings.held_item = Some(clip.item.to_owned());
+    if edit::add_delete_value(settings, &mut resources.config_plist, true) {
+        settings.add();
+    }
+}
+
+/// short description of the type and size of `item`
+fn kind(item: &Value) -> String {
+    match item {
+        Value::Array(a) => format!("array of {}", a.len()),
+        Value::Dictionary(d) => format!("dict of {}", d.len()),
+        Value::Boolean(b) => b.to_string(),
+        Value::Data(d) => format!("data, {} bytes", d.len()),
+        Value::Integer(i) => i.to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,122 @@
+use crate::lint::version_nums;
+use crate::migrate::{sample_for_version, FREE_FORM};
+use crate::res::Resources;
+
+use plist::Value;
+use serde_json::json;
+
+use std::error::Error;
+use std::io::{Stdout, Write};
+
+/// file in tool_config_files used to detect the OpenCore version of a config.plist,
+/// relative to the folder octool runs from
+pub const DIFFERENCES_FILE: &str = "tool_config_files/config_differences.json";
+
+/// read the Sample.plist of every OpenCorePkg version in the dortania builds, oldest
+/// first, and record the version each key first showed up in and the version it was
+/// removed in, then write them to the config_differences file
+/// returns the number of additions found
+pub fn regenerate(resources: &mut Resources, stdout: &mut Stdout) -> Result<usize, Box<dyn Error>> {
+    let mut versions = vec![];
+    let mut i = 0;
+    while let Some(v) = resources.dortania["OpenCorePkg"]["versions"][i]["version"].as_str() {
+        if !versions.iter().any(|s| s == v) {
+            versions.push(v.to_owned());
+        }
+        i += 1;
+    }
+    if versions.is_empty() {
+        return Err("no OpenCorePkg versions found in the dortania build list".into());
+    }
+    versions.sort_by_key(|v| version_nums(v));
+
+    let mut added = vec![];
+    let mut removed = vec![];
+    let mut last: Option<Vec<[String; 3]>> = None;
+    for ver in &versions {
+        write!(stdout, "\rreading Sample.plist for OpenCore {}\x1b[0K", ver).unwrap();
+        stdout.flush().unwrap();
+        let sample = match sample_for_version(ver) {
+            Ok(s) => s,
+            Err(e) => {
+                write!(
+                    stdout,
+                    "\r\n\x1b[33mWARNING:\x1b[0m skipping {}: {}\r\n",
+                    ver, e
+                )
+                .unwrap();
+                continue;
+            }
+        };
+        let mut keys = vec![];
+        key_paths(&sample, &mut vec![], &mut keys);
+        if let Some(last) = &last {
+            for key in keys.iter().filter(|k| !last.contains(k)) {
+                added.push((key.clone(), ver.to_owned()));
+            }
+            for key in last.iter().filter(|k| !keys.contains(k)) {
+                removed.push((key.clone(), ver.to_owned()));
+            }
+        }
+        last = Some(keys);
+    }
+    write!(stdout, "\r\x1b[0K").unwrap();
+
+    // newest versions first, the same as the dortania build list
+    let newest_first = |list: Vec<([String; 3], String)>| -> Vec<serde_json::Value> {
+        let mut list = list;
+        list.sort_by_key(|(_, v)| std::cmp::Reverse(version_nums(v)));
+        list.into_iter()
+            .map(|([sec, sub, key], ver)| json!([sec, sub, key, ver]))
+            .collect()
+    };
+    let count = added.len();
+    resources.config_differences = json!({
+        "config_differences": newest_first(added),
+        "config_removals": newest_first(removed),
+    });
+    std::fs::write(
+        resources.working_dir_path.join(DIFFERENCES_FILE),
+        serde_json::to_string_pretty(&resources.config_differences)?,
+    )?;
+    Ok(count)
+}
+
+/// gather the section, nearest named parent and name of every key below the sub
+/// section level of `val`, array items are looked through and comment keys skipped
+pub fn key_paths(val: &Value, path: &mut Vec<String>, keys: &mut Vec<[String; 3]>) {
+    if FREE_FORM.iter().any(|f| path == f) {
+        return;
+    }
+    match val {
+        Value::Dictionary(d) => {
+            for (key, v) in d {
+                if key.starts_with('#') {
+                    continue;
+                }
+                if path.len() >= 2 {
+                    let parent = path[1..]
+                        .iter()
+                        .rev()
+                        .find(|p| p.parse::<usize>().is_err())
+                        .unwrap();
+                    let entry = [path[0].to_owned(), parent.to_owned(), key.to_owned()];
+                    if !keys.contains(&entry) {
+                        keys.push(entry);
+                    }
+                }
+                path.push(key.to_owned());
+                key_paths(v, path, keys);
+                path.pop();
+            }
+        }
+        Value::Array(a) => {
+            for (i, v) in a.iter().enumerate() {
+                path.push(i.to_string());
+                key_paths(v, path, keys);
+                path.pop();
+            }
+        }
+        _ => (),
+    }
+}
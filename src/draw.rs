@@ -4,9 +4,12 @@ use std::error::Error;
 use std::io::{Stdout, Write};
 
 use crate::edit::read_key;
+use crate::filter;
 use crate::init::Settings;
+use crate::lint;
 use crate::parse_tex;
 use crate::res::{self, Resources};
+use crate::schema;
 
 use crossterm::cursor::position;
 use crossterm::event::KeyCode;
@@ -25,6 +28,8 @@ pub fn update_screen(
 ) -> Result<(), Box<dyn Error>> {
     let plist = &resources.config_plist;
     let screen_rows: i32 = size().unwrap().1.into();
+    settings.fit_depth(); // e.g. after jumping to a found item
+    filter::apply(settings);
     settings.can_expand = false;
     //    let bgc = &settings.bg_col.clone();
     let bgc = "\x1b[0m";
@@ -121,6 +126,15 @@ pub fn update_screen(
             write!(stdout, "  \x1B[7mspace{} toggle", bgc).unwrap();
         }
     }
+    if settings.inside_an_array {
+        write!(stdout, "  \x1B[7m[{} \x1B[7m]{} move", bgc, bgc).unwrap();
+        let marked = settings.marks.items_here(settings).len();
+        if marked > 0 {
+            write!(stdout, "  \x1B[7mB{}ulk {} marked", bgc, marked).unwrap();
+        } else {
+            write!(stdout, "  \x1B[7mm{}ark", bgc).unwrap();
+        }
+    }
     if settings.find_string.len() > 0 {
         write!(
             stdout,
@@ -129,6 +143,21 @@ pub fn update_screen(
         )
         .unwrap();
     }
+    let filtered = settings.filter.status();
+    if !filtered.is_empty() {
+        write!(
+            stdout,
+            "  \x1b[7m/{} filter \x1b[4m{}{}",
+            bgc, filtered, bgc
+        )
+        .unwrap();
+    }
+    if let Some(c) = settings.clipboard.pending {
+        write!(stdout, "  register \x1b[7m\"{}{}", c, bgc).unwrap();
+    }
+    if let Some(change) = settings.history.next_undo() {
+        write!(stdout, "  \x1b[7mu{}ndo \x1b[4m{}{}", bgc, change, bgc,).unwrap();
+    }
     if settings.held_key.len() > 0 {
         write!(
             stdout,
@@ -153,6 +182,9 @@ fn display_value(
     display_depth: usize,
     is_array_key: bool,
 ) -> Result<i32, Box<dyn Error>> {
+    if settings.filter.hides(display_depth, item_num) {
+        return Ok(0);
+    }
     let mut live_item = false;
     let mut selected_item = false;
     let mut save_curs_pos = String::new();
@@ -162,6 +194,15 @@ fn display_value(
     //    let bgc = &settings.bg_col.clone();
     let bgc = "\x1b[0m";
     write!(stdout, "\r\n{}\x1B[0K", "    ".repeat(display_depth))?; // indent to section and clear rest of line
+    if display_depth > 0
+        && settings.marks.is_marked(
+            &settings.sec_num[..display_depth],
+            settings.sec_length[display_depth],
+            item_num,
+        )
+    {
+        write!(stdout, "\x1B[2D\x1b[33m*\x1b[0m ")?; // marked for bulk actions
+    }
     if settings.sec_num[display_depth] == item_num {
         selected_item = true;
         settings.sec_key[display_depth] = key.to_string();
@@ -315,6 +356,10 @@ fn display_value(
                             Some(s) => s,
                             None => " \x1b[33m∆\x1b[0m ".to_string(),
                         }
+                    } else if settings.sec_key[1] == "Patch"
+                        && !lint::check_patch(&settings.sec_key[0], v).is_empty()
+                    {
+                        " \x1b[33m!\x1b[0m ".to_string()
                     } else {
                         "".to_string()
                     }
@@ -384,6 +429,32 @@ fn display_value(
         }
         _ => panic!("Can't handle this type"),
     }
+    if !matches!(plist_value, Value::Array(_) | Value::Dictionary(_)) {
+        // check the value against the Configuration.tex schema
+        let mut path = settings.raw_sec_key[..display_depth].to_vec();
+        path.push(raw_key.to_owned());
+        if let Some(entry) = schema::lookup(&resources.schema, &path) {
+            if let Some((problem, wrong_type)) = schema::check(entry, plist_value) {
+                write!(
+                    stdout,
+                    " {}?\x1b[0m",
+                    if wrong_type { "\x1b[31m" } else { "\x1b[33m" }
+                )?;
+                if live_item {
+                    settings
+                        .item_instructions
+                        .push_str(&format!("  \x1b[33m{}\x1b[0m", problem));
+                }
+            }
+            if live_item {
+                if let Some(failsafe) = entry["failsafe"].as_str().filter(|f| !f.is_empty()) {
+                    settings
+                        .item_instructions
+                        .push_str(&format!("  failsafe \x1b[4m{}\x1b[0m", failsafe));
+                }
+            }
+        }
+    }
     Ok(row)
 }
 
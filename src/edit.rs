@@ -1,3 +1,5 @@
+use crate::acpi;
+use crate::aml;
 use crate::draw;
 use crate::edit;
 use crate::init::Settings;
@@ -5,6 +7,7 @@ use crate::res::Resources;
 
 use crossterm::event::KeyModifiers;
 use plist::{Integer, Value};
+use regex::{Regex, RegexBuilder};
 
 use std::{
     error::Error,
@@ -21,7 +24,7 @@ use crossterm::{
 #[derive(Debug)]
 pub struct Found {
     pub level: usize,
-    pub section: [usize; 5],
+    pub section: Vec<usize>,
     pub keys: Vec<String>,
 }
 
@@ -29,7 +32,7 @@ impl Found {
     pub fn new() -> Found {
         Found {
             level: 0,
-            section: [0; 5],
+            section: vec![],
             keys: vec![],
         }
     }
@@ -188,101 +191,192 @@ pub fn add_delete_value(settings: &mut Settings, mut plist_val: &mut Value, add:
     changed
 }
 
-/// ask for a search string and give a scrollable list of locations to jump to in 'found'
-/// if only 1 result is found, jump immediately
-pub fn find(find_string: &str, resource: &plist::Value, found: &mut Vec<Found>) {
-    if find_string.len() > 0 {
-        let search = find_string.to_lowercase();
-        let resource = resource.as_dictionary().unwrap();
-        for (i, key) in resource.keys().enumerate() {
-            let low_key = key.to_lowercase();
-            if low_key.contains(&search) {
-                found.push(Found {
-                    level: 0,
-                    section: [i, 0, 0, 0, 0],
-                    keys: vec![key.to_owned()],
-                });
+/// the array holding the highlighted item, None if it isn't in an array
+pub fn section_array<'a>(
+    settings: &Settings,
+    mut plist_val: &'a mut Value,
+) -> Option<&'a mut Vec<Value>> {
+    for i in 0..settings.depth {
+        plist_val = match plist_val {
+            Value::Dictionary(d) => d.get_mut(&settings.sec_key[i])?,
+            Value::Array(a) => a.get_mut(settings.sec_num[i])?,
+            _ => return None,
+        }
+    }
+    match plist_val {
+        Value::Array(a) => Some(a),
+        _ => None,
+    }
+}
+
+/// move the items numbered `items` in the array holding the highlighted item one place
+/// up or down keeping the highlight on the same item, the items keep their order and
+/// gaps between them, returns the old number of the item now at each place, None if
+/// not in an array or an item is already at the end
+pub fn move_items(
+    settings: &mut Settings,
+    plist_val: &mut Value,
+    items: &[usize],
+    up: bool,
+) -> Option<Vec<usize>> {
+    let a = section_array(settings, plist_val)?;
+    let mut items = items.to_vec();
+    items.sort_unstable();
+    items.dedup();
+    match (items.first(), items.last()) {
+        (Some(first), Some(last)) if (up && *first > 0) || (!up && last + 1 < a.len()) => (),
+        _ => return None,
+    }
+    let mut order = (0..a.len()).collect::<Vec<usize>>();
+    let swaps = if up {
+        items
+            .iter()
+            .map(|i| (i - 1, *i))
+            .collect::<Vec<(usize, usize)>>()
+    } else {
+        items.iter().rev().map(|i| (*i, i + 1)).collect()
+    };
+    for (x, y) in swaps {
+        a.swap(x, y);
+        order.swap(x, y);
+    }
+    let selected = &mut settings.sec_num[settings.depth];
+    *selected = order
+        .iter()
+        .position(|o| o == selected)
+        .unwrap_or(*selected);
+    Some(order)
+}
+
+/// build the matcher for a search term, a term between slashes e.g. `/^Apple.*/` is
+/// a regex, anything else is a case insensitive plain text match
+pub fn search_regex(term: &str) -> Result<Regex, regex::Error> {
+    match term.strip_prefix('/').and_then(|t| t.strip_suffix('/')) {
+        Some(re) if !re.is_empty() => Regex::new(re),
+        _ => RegexBuilder::new(&regex::escape(term))
+            .case_insensitive(true)
+            .build(),
+    }
+}
+
+/// the texts a value can be found by, strings and integers as they are, data as
+/// hex and as ASCII, nothing for other types
+pub fn value_texts(val: &plist::Value) -> Vec<String> {
+    match val {
+        plist::Value::String(s) => vec![s.to_owned()],
+        plist::Value::Integer(i) => vec![i.to_string()],
+        plist::Value::Data(d) => vec![hex::encode_upper(d), String::from_utf8_lossy(d).to_string()],
+        _ => vec![],
+    }
+}
+
+/// index into value_texts of the first text of 'val' that 'search' matches, the hex
+/// text of data only matches whole bytes, so AB doesn't match <0AB0>
+pub fn value_match(search: &Regex, val: &plist::Value) -> Option<usize> {
+    value_texts(val).iter().enumerate().position(|(i, t)| {
+        if i == 0 && matches!(val, plist::Value::Data(_)) {
+            !hex_matches(search, t).is_empty()
+        } else {
+            search.is_match(t)
+        }
+    })
+}
+
+/// the matches of 'search' in the 'hex' text of data that start and end on a byte
+pub fn hex_matches(search: &Regex, hex: &str) -> Vec<std::ops::Range<usize>> {
+    let mut found = vec![];
+    let mut start = 0;
+    while start < hex.len() {
+        match search.find_at(hex, start) {
+            Some(m) if m.start() == start && !m.is_empty() && m.len() % 2 == 0 => {
+                found.push(m.range());
+                start = m.end();
             }
-            match resource.get(key).unwrap().as_dictionary() {
-                Some(sub) => {
-                    for (j, s_key) in sub.keys().enumerate() {
-                        let low_key = s_key.to_lowercase();
-                        if low_key.contains(&search) {
-                            found.push(Found {
-                                level: 1,
-                                section: [i, j, 0, 0, 0],
-                                keys: vec![key.to_owned(), s_key.to_owned()],
-                            });
-                        }
-                        let sub_sub = sub.get(s_key).unwrap();
-                        match sub_sub {
-                            plist::Value::Dictionary(d) => {
-                                for (k, s_s_key) in d.keys().enumerate() {
-                                    let low_key = s_s_key.to_lowercase();
-                                    if low_key.contains(&search) {
-                                        found.push(Found {
-                                            level: 2,
-                                            section: [i, j, k, 0, 0],
-                                            keys: vec![
-                                                key.to_owned(),
-                                                s_key.to_owned(),
-                                                s_s_key.to_owned(),
-                                            ],
-                                        });
-                                    }
-                                    match d.get(s_s_key).unwrap() {
-                                        plist::Value::Dictionary(sub_d) => {
-                                            for (l, sub_d_key) in sub_d.keys().enumerate() {
-                                                let low_key = sub_d_key.to_lowercase();
-                                                if low_key.contains(&search) {
-                                                    found.push(Found {
-                                                        level: 3,
-                                                        section: [i, j, k, l, 0],
-                                                        keys: vec![
-                                                            key.to_owned(),
-                                                            s_key.to_owned(),
-                                                            s_s_key.to_owned(),
-                                                            sub_d_key.to_owned(),
-                                                        ],
-                                                    });
-                                                }
-                                            }
-                                        }
-                                        _ => (),
-                                    } // end match d
-                                }
-                            }
-                            plist::Value::Array(a) => {
-                                for (k, v) in a.iter().enumerate() {
-                                    match v {
-                                        plist::Value::Dictionary(d) => {
-                                            for (l, s_s_key) in d.keys().enumerate() {
-                                                let low_key = s_s_key.to_lowercase();
-                                                if low_key.contains(&search) {
-                                                    found.push(Found {
-                                                        level: 3,
-                                                        section: [i, j, k, l, 0],
-                                                        keys: vec![
-                                                            key.to_owned(),
-                                                            s_key.to_owned(),
-                                                            k.to_string(),
-                                                            s_s_key.to_owned(),
-                                                        ],
-                                                    });
-                                                }
-                                            }
-                                        }
-                                        _ => (),
-                                    } // end match v
-                                }
-                            }
+            // nothing matches between start and m, try again at the first byte from m
+            Some(m) if m.start() > start => start = (m.start() + 1) & !1,
+            Some(_) => start += 2,
+            None => break,
+        }
+    }
+    found
+}
 
-                            _ => (),
-                        } // end match sub_sub
+/// find the keys and values matching 'search' in 'resource' and add their locations to
+/// 'found', if 'section' is not empty only the item at that position and what is below
+/// it are searched
+pub fn find(search: &Regex, resource: &plist::Value, section: &[usize], found: &mut Vec<Found>) {
+    let mut val = resource;
+    let mut keys = vec![];
+    for i in section {
+        val = match val {
+            plist::Value::Dictionary(d) => match d.iter().nth(*i) {
+                Some((k, v)) => {
+                    keys.push(k.to_owned());
+                    v
+                }
+                None => return,
+            },
+            plist::Value::Array(a) => match a.get(*i) {
+                Some(v) => {
+                    keys.push(i.to_string());
+                    v
+                }
+                None => return,
+            },
+            _ => return,
+        };
+    }
+    find_in(search, val, &mut section.to_vec(), &mut keys, found);
+}
+
+/// add the keys of 'val' and everything below it that match 'search' to 'found', or 'val'
+/// itself if its value matches, 'section' and 'keys' hold the position and names of 'val'
+fn find_in(
+    search: &Regex,
+    val: &plist::Value,
+    section: &mut Vec<usize>,
+    keys: &mut Vec<String>,
+    found: &mut Vec<Found>,
+) {
+    match val {
+        plist::Value::Dictionary(d) => {
+            for (i, (key, v)) in d.iter().enumerate() {
+                section.push(i);
+                keys.push(key.to_owned());
+                if search.is_match(key) {
+                    found.push(Found {
+                        level: section.len() - 1,
+                        section: section.clone(),
+                        keys: keys.clone(),
+                    });
+                    // don't list a matching value again
+                    if matches!(v, plist::Value::Dictionary(_) | plist::Value::Array(_)) {
+                        find_in(search, v, section, keys, found);
                     }
+                } else {
+                    find_in(search, v, section, keys, found);
                 }
-                _ => (),
-            } // end match resource
+                section.pop();
+                keys.pop();
+            }
+        }
+        plist::Value::Array(a) => {
+            for (i, v) in a.iter().enumerate() {
+                section.push(i);
+                keys.push(i.to_string());
+                find_in(search, v, section, keys, found);
+                section.pop();
+                keys.pop();
+            }
+        }
+        _ => {
+            if !section.is_empty() && value_match(search, val).is_some() {
+                found.push(Found {
+                    level: section.len() - 1,
+                    section: section.clone(),
+                    keys: keys.clone(),
+                });
+            }
         }
     }
 }
@@ -349,6 +443,15 @@ pub fn add_item(
         selection_adjust += 1;
         item_types.push(msg);
     }
+    let mut table_option = 0; // selection number of the Delete entry from table option
+    if settings.inside_an_array
+        && settings.depth == 2
+        && settings.sec_key[0] == "ACPI"
+        && settings.sec_key[1] == "Delete"
+    {
+        item_types.push("New ACPI > Delete entry from an .aml table header".to_string());
+        table_option = item_types.len();
+    }
     for s in [
         "plist array",
         "plist boolean",
@@ -402,9 +505,14 @@ pub fn add_item(
         selection = 1;
         selection_adjust = 2;
     }
+    let pick = menu_pick(selection, selection_adjust, table_option);
     //    if selection_adjust > 1 && selection < 3 {
-    if selection <= selection_adjust {
-        if selection == 1 && selection_adjust == 2 {
+    if pick == MenuPick::Table {
+        if !add_delete_from_table(settings, resources, stdout) {
+            return;
+        }
+    } else if let MenuPick::Template(template) = pick {
+        if template == 1 && selection_adjust == 2 {
             let new_val_set;
             let mut selected_res = res_list[0].clone();
             if auto_add.len() == 0 {
@@ -484,7 +592,7 @@ pub fn add_item(
                 return;
             }
         }
-    } else {
+    } else if let MenuPick::PlistType(plist_type) = pick {
         write!(
             stdout,
             "Enter key for new {} item: {}{}\x1B[0K\r\n\x1B[2K",
@@ -495,9 +603,19 @@ pub fn add_item(
         .unwrap();
         stdout.flush().unwrap();
         let mut key = String::new();
-        edit_string(&mut key, None, stdout).unwrap();
+        let valid_values = aml::edit_values(settings, &resources.input_dir_path, true);
+        edit_string(
+            &mut key,
+            if valid_values.is_empty() {
+                None
+            } else {
+                Some(&valid_values)
+            },
+            stdout,
+        )
+        .unwrap();
         settings.held_key = String::from(key.trim());
-        settings.held_item = Some(match selection - selection_adjust {
+        settings.held_item = Some(match plist_type {
             1 => plist::Value::Array(vec![]),
             2 => false.into(),
             3 => plist::Value::Data(vec![]),
@@ -513,6 +631,108 @@ pub fn add_item(
     }
 }
 
+/// what a selection of the add_item menu picks
+#[derive(Debug, PartialEq)]
+enum MenuPick {
+    Template(usize),  // resource list or Sample.plist template, numbered from 1
+    Table,            // ACPI > Delete entry from an .aml table header
+    PlistType(usize), // array, boolean, data, dict, integer, string, numbered from 1
+}
+
+/// the menu lists the `selection_adjust` templates, then the table option if
+/// `table_option` isn't 0, then the plist types
+fn menu_pick(selection: usize, selection_adjust: usize, table_option: usize) -> MenuPick {
+    if selection <= selection_adjust {
+        MenuPick::Template(selection)
+    } else if table_option > 0 && selection == table_option {
+        MenuPick::Table
+    } else if table_option > 0 && selection > table_option {
+        MenuPick::PlistType(selection - selection_adjust - 1)
+    } else {
+        MenuPick::PlistType(selection - selection_adjust)
+    }
+}
+
+/// ask for an .aml file and place an ACPI > Delete entry made from its table
+/// header into settings.held_item, returns false if cancelled or the file isn't
+/// an ACPI table
+fn add_delete_from_table(
+    settings: &mut Settings,
+    resources: &Resources,
+    stdout: &mut Stdout,
+) -> bool {
+    // offer the tables in the INPUT folder and any dump folders inside it
+    let mut tables = vec![];
+    for entry in walkdir::WalkDir::new(&resources.input_dir_path)
+        .max_depth(2)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let name = entry.path().to_string_lossy().to_string();
+        if name.to_lowercase().ends_with(".aml") {
+            tables.push(name);
+        }
+    }
+    let mut table = tables.first().cloned().unwrap_or_default();
+    write!(
+        stdout,
+        "{}{}\r\x1B[2K{} the .aml table: {}\r\n\x1B[2K\x1B8",
+        cursor::RestorePosition,
+        cursor::Show,
+        if tables.is_empty() {
+            "No tables found in INPUT, enter the path of, or drop"
+        } else {
+            "Select, enter the path of, or drop"
+        },
+        cursor::SavePosition,
+    )
+    .unwrap();
+    let new_val_set = edit_string(
+        &mut table,
+        if tables.is_empty() {
+            None
+        } else {
+            Some(&tables)
+        },
+        stdout,
+    )
+    .unwrap();
+    write!(stdout, "{}", cursor::Hide).unwrap();
+    if !new_val_set {
+        return false;
+    }
+    let table = table.replace("\\ ", " ");
+    let header = match acpi::inspect(std::path::Path::new(table.trim())) {
+        Ok(h) => h,
+        Err(e) => {
+            write!(
+                stdout,
+                "\r\n\x1B[31mERROR:\x1B[0m {}: {}\x1B[0K\r\n\x1B[2K",
+                table, e
+            )
+            .unwrap();
+            read_key().unwrap();
+            return false;
+        }
+    };
+    if !extract_value(settings, &resources.sample_plist, true, false) {
+        return false;
+    }
+    if let Some(Value::Dictionary(d)) = settings.held_item.as_mut() {
+        acpi::fill_delete_entry(d, &header);
+    }
+    write!(
+        stdout,
+        "\r\n\x1B[32mAdding\x1B[0m TableSignature <{}> OemTableId <{}> TableLength {}\x1B[0K\r\n",
+        draw::hex_str_with_style(hex::encode(header.signature.as_bytes())),
+        draw::hex_str_with_style(hex::encode(&header.raw_oem_table_id)),
+        header.length,
+    )
+    .unwrap();
+    true
+}
+
 /// edit the highlighted value in the loaded config.plist
 ///
 /// ```
@@ -852,6 +1072,21 @@ fn edit_int(val: &mut Integer, valid_values: Option<&Vec<String>>, stdout: &mut
     }
 }
 
+/// show `message` and edit `val`, returns false if cancelled
+pub fn prompt(message: &str, val: &mut String, stdout: &mut Stdout) -> bool {
+    write!(
+        stdout,
+        "\r\n{}\r\x1B[2K{} {}\r\n\x1B[2K\x1B8",
+        cursor::Show,
+        message,
+        cursor::SavePosition,
+    )
+    .unwrap();
+    let set = edit_string(val, None, stdout).unwrap();
+    write!(stdout, "{}", cursor::Hide).unwrap();
+    set
+}
+
 pub fn edit_string(
     val: &mut String,
     valid_values: Option<&Vec<String>>,
@@ -987,3 +1222,121 @@ pub fn edit_string(
     }
     Ok(new_val_set)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// settings with the highlight on item `item` of the Kernel > Add array
+    fn in_kernel_add(item: usize, len: usize) -> Settings {
+        Settings {
+            depth: 2,
+            sec_key: vec!["Kernel".to_string(), "Add".to_string(), item.to_string()],
+            sec_num: vec![0, 0, item],
+            sec_length: vec![1, 1, len],
+            inside_an_array: true,
+            ..Default::default()
+        }
+    }
+
+    fn kernel_add(len: i64) -> plist::Value {
+        let items = (0..len).map(|i| plist::Value::Integer(i.into())).collect();
+        let mut kernel = plist::Dictionary::new();
+        kernel.insert("Add".to_string(), plist::Value::Array(items));
+        let mut root = plist::Dictionary::new();
+        root.insert("Kernel".to_string(), plist::Value::Dictionary(kernel));
+        plist::Value::Dictionary(root)
+    }
+
+    fn order(plist: &mut plist::Value) -> Vec<i64> {
+        section_array(&in_kernel_add(0, 0), plist)
+            .unwrap()
+            .iter()
+            .map(|v| v.as_signed_integer().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn moves_one_item() {
+        let mut settings = in_kernel_add(2, 4);
+        let mut plist = kernel_add(4);
+        let moved = move_items(&mut settings, &mut plist, &[2], true);
+        assert_eq!(moved, Some(vec![0, 2, 1, 3]));
+        assert_eq!(order(&mut plist), vec![0, 2, 1, 3]);
+        assert_eq!(settings.sec_num[2], 1);
+        assert_eq!(move_items(&mut settings, &mut plist, &[3], false), None);
+    }
+
+    #[test]
+    fn moves_items_keeping_gaps() {
+        let mut settings = in_kernel_add(1, 6);
+        let mut plist = kernel_add(6);
+        let moved = move_items(&mut settings, &mut plist, &[3, 1, 4], false);
+        assert_eq!(moved, Some(vec![0, 2, 1, 5, 3, 4]));
+        assert_eq!(order(&mut plist), vec![0, 2, 1, 5, 3, 4]);
+        assert_eq!(settings.sec_num[2], 2);
+        let moved = move_items(&mut settings, &mut plist, &[2, 4, 5], true);
+        assert_eq!(moved, Some(vec![0, 2, 1, 4, 5, 3]));
+        assert_eq!(order(&mut plist), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(settings.sec_num[2], 1);
+        assert_eq!(move_items(&mut settings, &mut plist, &[0, 2], true), None);
+    }
+
+    #[test]
+    fn moves_only_inside_arrays() {
+        let mut settings = in_kernel_add(0, 1);
+        settings.depth = 1;
+        assert_eq!(
+            move_items(&mut settings, &mut kernel_add(2), &[0], false),
+            None
+        );
+    }
+
+    #[test]
+    fn picks_every_add_menu_slot() {
+        // ACPI > Delete, 1 Sample template, 2 entry from a table header, 3-8 plist types
+        assert_eq!(menu_pick(1, 1, 2), MenuPick::Template(1));
+        assert_eq!(menu_pick(2, 1, 2), MenuPick::Table);
+        for slot in 3..=8 {
+            assert_eq!(menu_pick(slot, 1, 2), MenuPick::PlistType(slot - 2));
+        }
+        // a resource section, 1 resource list, 2 Sample template, 3-8 plist types
+        for slot in 1..=2 {
+            assert_eq!(menu_pick(slot, 2, 0), MenuPick::Template(slot));
+        }
+        for slot in 3..=8 {
+            assert_eq!(menu_pick(slot, 2, 0), MenuPick::PlistType(slot - 2));
+        }
+        // outside an array only the plist types
+        for slot in 1..=6 {
+            assert_eq!(menu_pick(slot, 0, 0), MenuPick::PlistType(slot));
+        }
+    }
+
+    #[test]
+    fn hex_matches_whole_bytes() {
+        let search = search_regex("AB").unwrap();
+        assert!(hex_matches(&search, "0AB0").is_empty());
+        assert_eq!(hex_matches(&search, "0AB0AB"), vec![4..6]);
+        assert_eq!(hex_matches(&search, "ABAB"), vec![0..2, 2..4]);
+        let odd = search_regex("/A/").unwrap();
+        assert!(hex_matches(&odd, "AA").is_empty());
+    }
+
+    #[test]
+    fn data_matches_on_bytes_or_text() {
+        let search = search_regex("AB").unwrap();
+        assert_eq!(
+            value_match(&search, &plist::Value::Data(vec![0x0A, 0xB0])),
+            None
+        );
+        assert_eq!(
+            value_match(&search, &plist::Value::Data(vec![0xAB])),
+            Some(0)
+        );
+        assert_eq!(
+            value_match(&search, &plist::Value::Data(b"xab".to_vec())),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::edit::{prompt, read_key, search_regex, value_match};
+use crate::init::Settings;
+use crate::lint;
+use crate::res::Resources;
+use crate::schema;
+
+use crossterm::event::KeyCode;
+use plist::Value;
+use regex::Regex;
+
+use std::io::{Stdout, Write};
+
+/// which items of the filtered section are shown
+#[derive(Clone, Debug, Default)]
+pub enum Kind {
+    #[default]
+    All,
+    Enabled,      // enabled entries and true booleans
+    Changed,      // values that differ from the Sample.plist
+    Lint,         // entries with lint or schema problems
+    Text(String), // entries with a key or value matching the text or /regex/
+}
+
+/// a view filter for one long section, e.g. Kernel > Add or DeviceProperties > Add
+#[derive(Debug, Default)]
+pub struct ViewFilter {
+    pub kind: Kind,
+    section: Vec<usize>, // sec_num of the section filtered
+    here: bool,          // true if the highlighted item is in the filtered section
+    active: bool,        // here and something passed the filter
+    shown: Vec<usize>,   // items of the section that pass the filter
+    total: usize,        // number of items in the section
+}
+
+impl ViewFilter {
+    /// true if item `item` of the list at `depth` is filtered out of the screen
+    pub fn hides(&self, depth: usize, item: usize) -> bool {
+        self.active && depth == self.section.len() && !self.shown.contains(&item)
+    }
+
+    /// header text describing the filter, empty if there is none
+    pub fn status(&self) -> String {
+        let name = match &self.kind {
+            Kind::All => return String::new(),
+            Kind::Enabled => "enabled".to_string(),
+            Kind::Changed => "changed".to_string(),
+            Kind::Lint => "lint".to_string(),
+            Kind::Text(t) => format!("\"{}\"", t),
+        };
+        if self.active {
+            format!("{} {} of {}", name, self.shown.len(), self.total)
+        } else if self.here {
+            format!("{} matches nothing", name)
+        } else {
+            format!("{} in another section", name)
+        }
+    }
+}
+
+/// ask for the filter to use for the section holding the highlighted item
+pub fn select(settings: &mut Settings, resources: &Resources, stdout: &mut Stdout) {
+    if settings.depth == 0 {
+        return;
+    }
+    write!(
+        stdout,
+        "\r\n\x1b[2KShow only '\x1b[32me\x1b[0m' enabled entries, '\x1b[32mc\x1b[0m' entries changed from \
+         the Sample.plist, '\x1b[32ml\x1b[0m' entries with lint warnings, '\x1b[32mt\x1b[0m' entries matching \
+         a text or /regex/, '\x1b[32ma\x1b[0m' show all\x1b[0K\r\n\x1b[2K"
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    let kind = match read_key().unwrap().0 {
+        KeyCode::Char('e') => Kind::Enabled,
+        KeyCode::Char('c') => Kind::Changed,
+        KeyCode::Char('l') => Kind::Lint,
+        KeyCode::Char('t') => {
+            let mut text = String::new();
+            if !prompt("Show entries matching text or /regex/:", &mut text, stdout)
+                || text.is_empty()
+            {
+                return;
+            }
+            if let Err(e) = search_regex(&text) {
+                write!(
+                    stdout,
+                    "\r\n\x1b[31mERROR:\x1b[0m {}\x1b[0K\r\n\x1b[2Kpress any key to continue",
+                    e
+                )
+                .unwrap();
+                stdout.flush().unwrap();
+                read_key().unwrap();
+                return;
+            }
+            Kind::Text(text)
+        }
+        KeyCode::Char('a') => Kind::All,
+        _ => return,
+    };
+    settings.filter = ViewFilter {
+        kind,
+        section: settings.sec_num[..settings.depth].to_vec(),
+        ..Default::default()
+    };
+    refresh(settings, resources);
+}
+
+/// before the screen is drawn, move the highlight off an item the filter hides if it
+/// is in the filtered section, if nothing passes everything is shown
+pub fn apply(settings: &mut Settings) {
+    let filter = &mut settings.filter;
+    filter.here = !matches!(filter.kind, Kind::All)
+        && settings.depth == filter.section.len()
+        && settings.sec_num[..settings.depth] == filter.section;
+    filter.active = filter.here && !filter.shown.is_empty();
+    if !filter.active {
+        return;
+    }
+    let selected = &mut settings.sec_num[settings.depth];
+    if !filter.shown.contains(selected) {
+        *selected = match filter.shown.iter().find(|i| *i > selected) {
+            Some(i) => *i,
+            None => *filter.shown.last().unwrap(),
+        };
+    }
+}
+
+/// work out which items of the filtered section pass the filter, done when the filter
+/// is picked and after each change to the config.plist rather than on every redraw
+pub fn refresh(settings: &mut Settings, resources: &Resources) {
+    let filter = &mut settings.filter;
+    filter.total = 0;
+    filter.shown = vec![];
+    if matches!(filter.kind, Kind::All) {
+        return;
+    }
+    // walk the config.plist and Sample.plist down to the section
+    let mut keys = vec![];
+    let mut val = &resources.config_plist;
+    let mut sample = Some(&resources.sample_plist);
+    for i in &filter.section {
+        let (key, next) = match val {
+            Value::Dictionary(d) => match d.iter().nth(*i) {
+                Some((k, v)) => (k.to_owned(), v),
+                None => return,
+            },
+            Value::Array(a) => match a.get(*i) {
+                Some(v) => (i.to_string(), v),
+                None => return,
+            },
+            _ => return,
+        };
+        sample = sample.and_then(|s| match s {
+            Value::Dictionary(d) => d.get(&key),
+            Value::Array(a) => a.get(*i),
+            _ => None,
+        });
+        keys.push(key);
+        val = next;
+    }
+    let in_array = matches!(val, Value::Array(_));
+    let items: Vec<(String, &Value)> = match val {
+        Value::Dictionary(d) => d.iter().map(|(k, v)| (k.to_owned(), v)).collect(),
+        Value::Array(a) => a
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => return,
+    };
+    let problems = match filter.kind {
+        Kind::Lint => lint_problems(&keys, resources),
+        _ => vec![],
+    };
+    let search = match &filter.kind {
+        Kind::Text(t) => search_regex(t).ok(),
+        _ => None,
+    };
+    filter.total = items.len();
+    for (i, (key, item)) in items.iter().enumerate() {
+        let mut path = keys.clone();
+        path.push(key.to_owned());
+        let pass = match &filter.kind {
+            Kind::All => true,
+            Kind::Enabled => is_enabled(item),
+            Kind::Changed => match sample {
+                Some(Value::Dictionary(d)) => d.get(key) != Some(item),
+                Some(Value::Array(a)) => !a.contains(item),
+                _ => true,
+            },
+            Kind::Lint => {
+                let joined = path.join("->");
+                problems
+                    .iter()
+                    .any(|p| *p == joined || p.starts_with(&format!("{}->", joined)))
+                    || has_schema_problem(item, &mut path, resources)
+            }
+            Kind::Text(_) => search
+                .as_ref()
+                .map(|s| matches(s, if in_array { "" } else { key }, item))
+                .unwrap_or(true),
+        };
+        if pass {
+            filter.shown.push(i);
+        }
+    }
+}
+
+/// true for true booleans and for entries that are not disabled
+fn is_enabled(item: &Value) -> bool {
+    match item {
+        Value::Boolean(b) => *b,
+        Value::Dictionary(d) => match d.get("Load") {
+            Some(Value::String(s)) => s != "Disabled",
+            _ => lint::is_enabled(item),
+        },
+        Value::String(_) => lint::is_enabled(item),
+        _ => false,
+    }
+}
+
+/// key paths, joined with ->, of the lint problems in the section at `keys`
+fn lint_problems(keys: &[String], resources: &Resources) -> Vec<String> {
+    let config = &resources.config_plist;
+    let mut found = lint::check_rules(config, resources)
+        .into_iter()
+        .map(|p| p.path)
+        .collect::<Vec<String>>();
+    for dupe in lint::find_duplicates(config) {
+        found.push(format!("{}->{}->{}", dupe.sec, dupe.sub, dupe.dup));
+    }
+    for patch in lint::find_patch_problems(config) {
+        found.push(format!("{}->Patch->{}", patch.sec, patch.index));
+    }
+    let prefix = format!("{}->", keys.join("->"));
+    found.retain(|p| p.starts_with(&prefix));
+    found
+}
+
+/// true if a value in `val` doesn't fit the Configuration.tex schema, `path` is
+/// the raw keys down to `val`
+fn has_schema_problem(val: &Value, path: &mut Vec<String>, resources: &Resources) -> bool {
+    let children: Vec<(String, &Value)> = match val {
+        Value::Dictionary(d) => d.iter().map(|(k, v)| (k.to_owned(), v)).collect(),
+        Value::Array(a) => a
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => {
+            return schema::lookup(&resources.schema, path)
+                .and_then(|entry| schema::check(entry, val))
+                .is_some()
+        }
+    };
+    for (key, child) in children {
+        path.push(key);
+        let found = has_schema_problem(child, path, resources);
+        path.pop();
+        if found {
+            return true;
+        }
+    }
+    false
+}
+
+/// true if `key` or a key or value inside `val` matches `search`
+fn matches(search: &Regex, key: &str, val: &Value) -> bool {
+    if search.is_match(key) {
+        return true;
+    }
+    match val {
+        Value::Dictionary(d) => d.iter().any(|(k, v)| matches(search, k, v)),
+        Value::Array(a) => a.iter().any(|v| matches(search, "", v)),
+        _ => value_match(search, val).is_some(),
+    }
+}
@@ -0,0 +1,333 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use plist::Value;
+
+/// number of changes kept for undo
+const MAX_STEPS: usize = 100;
+
+/// a config.plist state that can be gone back to, with where the cursor was
+#[derive(Debug)]
+struct Step {
+    description: String,
+    plist: Value,
+    depth: usize,
+    sec_num: Vec<usize>,
+}
+
+/// undo and redo stacks of whole config.plist states, newest last
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+}
+
+impl History {
+    /// remember the config.plist as it was before a change, `after` is the changed
+    /// plist used to describe the change, returns false if nothing changed
+    pub fn record(
+        &mut self,
+        action: &str,
+        before: Value,
+        after: &Value,
+        depth: usize,
+        sec_num: Vec<usize>,
+    ) -> bool {
+        if &before == after {
+            return false;
+        }
+        let description = format!("{} {}", action, describe(&before, after));
+        self.undo.push(Step {
+            description,
+            plist: before,
+            depth,
+            sec_num,
+        });
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        true
+    }
+
+    /// swap `plist` and the cursor for the state before the last change, returns the
+    /// description of the change undone
+    pub fn undo(
+        &mut self,
+        plist: &mut Value,
+        depth: &mut usize,
+        sec_num: &mut Vec<usize>,
+    ) -> Option<String> {
+        let step = self.undo.pop()?;
+        let description = step.description.to_owned();
+        self.redo.push(swap(step, plist, depth, sec_num));
+        Some(description)
+    }
+
+    /// put back the last change undone, returns its description
+    pub fn redo(
+        &mut self,
+        plist: &mut Value,
+        depth: &mut usize,
+        sec_num: &mut Vec<usize>,
+    ) -> Option<String> {
+        let step = self.redo.pop()?;
+        let description = step.description.to_owned();
+        self.undo.push(swap(step, plist, depth, sec_num));
+        Some(description)
+    }
+
+    /// forget every step, e.g. when the OpenCore version changes and the saved
+    /// config.plist states no longer fit the Sample.plist in use
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// description of the change the next undo would take back
+    pub fn next_undo(&self) -> Option<&str> {
+        self.undo.last().map(|s| s.description.as_str())
+    }
+}
+
+/// name of the change a key press may make to the config.plist, None for keys
+/// that only move around or show things
+pub fn action(key: KeyCode, key_mod: KeyModifiers) -> Option<&'static str> {
+    let ctrl = key_mod == KeyModifiers::CONTROL;
+    match key {
+        KeyCode::Char(' ') => Some("Toggle"),
+        KeyCode::Char('i') => Some("Comment"),
+        KeyCode::Enter | KeyCode::Tab | KeyCode::Char('K') => Some("Edit"),
+        KeyCode::Char('a') => Some("Add"),
+        KeyCode::Char('p') | KeyCode::Char('"') => Some("Paste"),
+        KeyCode::Char('v') if ctrl => Some("Paste"),
+        KeyCode::Char('d') | KeyCode::Char('D') => Some("Delete"),
+        KeyCode::Char('x') if ctrl => Some("Cut"),
+        KeyCode::Char('r') if !ctrl => Some("Reset"),
+        KeyCode::Char('M') => Some("Merge"),
+        KeyCode::Char('I') => Some("Insert"),
+        KeyCode::Char('P') => Some("Purge"),
+        KeyCode::Char('F') => Some("Replace"),
+        KeyCode::Char('[') | KeyCode::Char(']') => Some("Move"),
+        KeyCode::Char('B') => Some("Bulk"),
+        KeyCode::Char('O') => Some("Order fix"),
+        KeyCode::Char('N') => Some("Snapshot"),
+        KeyCode::Char('g') => Some("SSDT"),
+        KeyCode::Char('A') => Some("Preset"),
+        KeyCode::Char('H') => Some("Hardware kexts"),
+        KeyCode::Char('L') => Some("Lint"),
+        _ => None,
+    }
+}
+
+/// put the state in `step` in place and return a step holding the one replaced
+fn swap(step: Step, plist: &mut Value, depth: &mut usize, sec_num: &mut Vec<usize>) -> Step {
+    let old = Step {
+        description: step.description,
+        plist: std::mem::replace(plist, step.plist),
+        depth: *depth,
+        sec_num: std::mem::replace(sec_num, step.sec_num),
+    };
+    *depth = step.depth;
+    old
+}
+
+/// short summary of the keys that differ between `before` and `after`, the path
+/// of the key if only one did, e.g. "changed Misc->Boot->Timeout"
+fn describe(before: &Value, after: &Value) -> String {
+    let mut counts = [0; 3]; // changed, added, removed
+    let mut first = None;
+    count_changes(before, after, &mut vec![], &mut counts, &mut first);
+    let names = ["changed", "added", "removed"];
+    if counts.iter().sum::<usize>() == 1 {
+        let i = counts.iter().position(|c| *c == 1).unwrap();
+        return format!("{} {}", names[i], first.unwrap_or_default());
+    }
+    let parts = names
+        .iter()
+        .zip(counts)
+        .filter(|(_, c)| *c > 0)
+        .map(|(n, c)| format!("{} {}", n, c))
+        .collect::<Vec<String>>();
+    format!("{} keys", parts.join(", "))
+}
+
+/// count the keys changed, added and removed going from `before` to `after`
+/// arrays of different lengths are compared by item, so removing one item is one
+/// removal rather than a change to every item after it
+fn count_changes(
+    before: &Value,
+    after: &Value,
+    path: &mut Vec<String>,
+    counts: &mut [usize; 3],
+    first: &mut Option<String>,
+) {
+    match (before, after) {
+        (Value::Dictionary(b), Value::Dictionary(a)) => {
+            for (k, v) in b {
+                path.push(k.to_owned());
+                match a.get(k) {
+                    Some(new) => count_changes(v, new, path, counts, first),
+                    None => note(2, path, counts, first),
+                }
+                path.pop();
+            }
+            for k in a.keys().filter(|k| !b.contains_key(k)) {
+                path.push(k.to_owned());
+                note(1, path, counts, first);
+                path.pop();
+            }
+        }
+        (Value::Array(b), Value::Array(a)) if b.len() == a.len() => {
+            for (i, (v, new)) in b.iter().zip(a).enumerate() {
+                path.push(i.to_string());
+                count_changes(v, new, path, counts, first);
+                path.pop();
+            }
+        }
+        (Value::Array(b), Value::Array(a)) => {
+            let noted = counts.iter().sum::<usize>();
+            for (i, _) in b.iter().enumerate().filter(|(_, v)| !a.contains(v)) {
+                path.push(i.to_string());
+                note(2, path, counts, first);
+                path.pop();
+            }
+            for (i, _) in a.iter().enumerate().filter(|(_, v)| !b.contains(v)) {
+                path.push(i.to_string());
+                note(1, path, counts, first);
+                path.pop();
+            }
+            // e.g. a copy of an item that is already there was added
+            if counts.iter().sum::<usize>() == noted {
+                note(if a.len() > b.len() { 1 } else { 2 }, path, counts, first);
+            }
+        }
+        _ => {
+            if before != after {
+                note(0, path, counts, first);
+            }
+        }
+    }
+}
+
+fn note(i: usize, path: &[String], counts: &mut [usize; 3], first: &mut Option<String>) {
+    counts[i] += 1;
+    if first.is_none() {
+        *first = Some(path.join("->"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dict(pairs: &[(&str, i64)]) -> Value {
+        Value::Dictionary(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::Integer((*v).into())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn undoes_and_redoes() {
+        let mut history = History::default();
+        let first = dict(&[("Timeout", 0)]);
+        let mut plist = dict(&[("Timeout", 5)]);
+        assert!(history.record("Edit", first.clone(), &plist, 1, vec![0, 0]));
+        assert_eq!(history.next_undo(), Some("Edit changed Timeout"));
+
+        let (mut depth, mut sec_num) = (0, vec![3]);
+        let undone = history.undo(&mut plist, &mut depth, &mut sec_num);
+        assert_eq!(undone.as_deref(), Some("Edit changed Timeout"));
+        assert_eq!(plist, first);
+        assert_eq!((depth, sec_num.clone()), (1, vec![0, 0]));
+        assert!(history.undo(&mut plist, &mut depth, &mut sec_num).is_none());
+
+        let redone = history.redo(&mut plist, &mut depth, &mut sec_num);
+        assert_eq!(redone.as_deref(), Some("Edit changed Timeout"));
+        assert_eq!(plist, dict(&[("Timeout", 5)]));
+        assert_eq!((depth, sec_num), (0, vec![3]));
+    }
+
+    #[test]
+    fn new_changes_clear_redo() {
+        let mut history = History::default();
+        let mut plist = dict(&[("A", 1)]);
+        assert!(!history.record("Edit", plist.clone(), &plist, 0, vec![0]));
+        history.record("Add", dict(&[]), &plist, 0, vec![0]);
+        let (mut depth, mut sec_num) = (0, vec![0]);
+        history.undo(&mut plist, &mut depth, &mut sec_num);
+        history.record("Add", dict(&[]), &dict(&[("B", 1)]), 0, vec![0]);
+        assert!(history.redo(&mut plist, &mut depth, &mut sec_num).is_none());
+        history.clear();
+        assert!(history.next_undo().is_none());
+    }
+
+    #[test]
+    fn keeps_the_last_steps() {
+        let mut history = History::default();
+        for i in 0..MAX_STEPS as i64 + 5 {
+            history.record(
+                "Edit",
+                dict(&[("A", i)]),
+                &dict(&[("A", i + 1)]),
+                0,
+                vec![0],
+            );
+        }
+        let mut plist = dict(&[]);
+        let (mut depth, mut sec_num) = (0, vec![0]);
+        let mut steps = 0;
+        while history.undo(&mut plist, &mut depth, &mut sec_num).is_some() {
+            steps += 1;
+        }
+        assert_eq!(steps, MAX_STEPS);
+        assert_eq!(plist, dict(&[("A", 5)]));
+    }
+
+    #[test]
+    fn describes_changes() {
+        let before = dict(&[("A", 1), ("B", 2)]);
+        assert_eq!(describe(&before, &dict(&[("A", 1)])), "removed B");
+        assert_eq!(
+            describe(&before, &dict(&[("A", 2), ("C", 3)])),
+            "changed 1, added 1, removed 1 keys"
+        );
+        let items = |list: Vec<i64>| {
+            let list = list.into_iter().map(|i| Value::Integer(i.into())).collect();
+            Value::Dictionary(
+                [("Add".to_string(), Value::Array(list))]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            describe(&items(vec![0, 1, 2]), &items(vec![0, 2])),
+            "removed Add->1"
+        );
+        // a copy of an item already there is counted as added to the array
+        assert_eq!(describe(&items(vec![0]), &items(vec![0, 0])), "added Add");
+    }
+
+    #[test]
+    fn names_actions() {
+        assert_eq!(
+            action(KeyCode::Char('I'), KeyModifiers::NONE),
+            Some("Insert")
+        );
+        assert_eq!(
+            action(KeyCode::Char('M'), KeyModifiers::NONE),
+            Some("Merge")
+        );
+        assert_eq!(action(KeyCode::Char('V'), KeyModifiers::NONE), None);
+        assert_eq!(
+            action(KeyCode::Char('i'), KeyModifiers::NONE),
+            Some("Comment")
+        );
+        assert_eq!(action(KeyCode::Char('r'), KeyModifiers::CONTROL), None);
+        assert_eq!(
+            action(KeyCode::Char('x'), KeyModifiers::CONTROL),
+            Some("Cut")
+        );
+    }
+}
@@ -0,0 +1,623 @@
+use crate::edit::read_key;
+use crate::init::Settings;
+use crate::preset;
+use crate::res::{self, Resources};
+use crate::snapshot;
+
+use crossterm::event::KeyCode;
+use plist::Value;
+
+use std::fs;
+use std::io::{Stdout, Write};
+use std::path::Path;
+
+// PCI class and subclass, the top 16 bits of the class file
+const CLASS_NVME: u32 = 0x0108;
+const CLASS_ETHERNET: u32 = 0x0200;
+const CLASS_WIRELESS: u32 = 0x0280;
+const CLASS_VGA: u32 = 0x0300;
+const CLASS_DISPLAY: u32 = 0x0380;
+const CLASS_AUDIO: u32 = 0x0403;
+const CLASS_USB: u32 = 0x0c03;
+const CLASS_SERIAL_OTHER: u32 = 0x0c80;
+const CLASS_SIGNAL_PROC: u32 = 0x1180; // Intel serial IO I2C controllers on most laptops
+
+const VENDOR_INTEL: u16 = 0x8086;
+const VENDOR_AMD: u16 = 0x1002;
+const VENDOR_NVIDIA: u16 = 0x10de;
+const VENDOR_REALTEK: u16 = 0x10ec;
+const VENDOR_BROADCOM: u16 = 0x14e4;
+const VENDOR_ATHEROS: u16 = 0x1969;
+
+/// kexts for PCI devices, (vendor, device ids or empty for any, class, kexts)
+/// the first matching rule is used for each device
+const PCI_RULES: &[(u16, &[u16], u32, &[&str])] = &[
+    (
+        VENDOR_INTEL,
+        &[0x15f2, 0x15f3, 0x125b, 0x125c],
+        CLASS_ETHERNET,
+        &["AppleIGC.kext"],
+    ),
+    (VENDOR_INTEL, &[], CLASS_ETHERNET, &["IntelMausi.kext"]),
+    (
+        VENDOR_REALTEK,
+        &[0x8125],
+        CLASS_ETHERNET,
+        &["LucyRTL8125Ethernet.kext"],
+    ),
+    (
+        VENDOR_REALTEK,
+        &[],
+        CLASS_ETHERNET,
+        &["RealtekRTL8111.kext"],
+    ),
+    (
+        VENDOR_ATHEROS,
+        &[],
+        CLASS_ETHERNET,
+        &["AtherosE2200Ethernet.kext"],
+    ),
+    (VENDOR_INTEL, &[], CLASS_WIRELESS, &["AirportItlwm.kext"]),
+    (
+        VENDOR_BROADCOM,
+        &[],
+        CLASS_WIRELESS,
+        &["AirportBrcmFixup.kext"],
+    ),
+    (0, &[], CLASS_AUDIO, &["AppleALC.kext"]),
+    (0, &[], CLASS_VGA, &["WhateverGreen.kext"]),
+    (0, &[], CLASS_DISPLAY, &["WhateverGreen.kext"]),
+    (0, &[], CLASS_NVME, &["NVMeFix.kext"]),
+];
+
+/// kexts for USB devices, (idVendor, kexts)
+const USB_RULES: &[(u16, &[&str])] = &[
+    (
+        0x8087,
+        &["IntelBluetoothFirmware.kext", "BlueToolFixup.kext"],
+    ),
+    (
+        0x0a5c,
+        &[
+            "BrcmPatchRAM3.kext",
+            "BrcmFirmwareData.kext",
+            "BlueToolFixup.kext",
+        ],
+    ),
+];
+
+/// Intel family 6 models and the generation they belong to, (models, id, name)
+const INTEL_GENERATIONS: &[(&[u32], &str, &str)] = &[
+    (&[0x17, 0x1d], "penryn", "Penryn"),
+    (
+        &[0x1a, 0x1e, 0x1f, 0x2e, 0x25, 0x2c, 0x2f],
+        "nehalem",
+        "Nehalem/Westmere",
+    ),
+    (&[0x2a, 0x2d], "sandy_bridge", "Sandy Bridge"),
+    (&[0x3a, 0x3e], "ivy_bridge", "Ivy Bridge"),
+    (&[0x3c, 0x3f, 0x45, 0x46], "haswell", "Haswell"),
+    (&[0x3d, 0x47, 0x4f, 0x56], "broadwell", "Broadwell"),
+    (&[0x4e, 0x5e, 0x55], "skylake", "Skylake"),
+    (&[0xa5, 0xa6], "comet_lake", "Comet Lake"),
+    (&[0x7d, 0x7e], "ice_lake", "Ice Lake"),
+    (&[0xa7], "rocket_lake", "Rocket Lake"),
+    (&[0x97, 0x9a], "alder_lake", "Alder Lake"),
+    (&[0xb7, 0xba, 0xbf], "raptor_lake", "Raptor Lake"),
+];
+
+/// DMI chassis types of portable machines
+const LAPTOP_CHASSIS: &[u32] = &[8, 9, 10, 14, 30, 31, 32];
+
+/// a device found on the PCI bus
+#[derive(Debug, Default)]
+pub struct PciDevice {
+    pub address: String, // e.g. 0000:00:1f.3
+    pub vendor: u16,
+    pub device: u16,
+    pub class: u32, // class, subclass and programming interface
+}
+
+/// what could be learned about the machine from sysfs and procfs
+#[derive(Debug, Default)]
+pub struct Profile {
+    pub sys_vendor: String,
+    pub product_name: String,
+    pub board_name: String,
+    pub laptop: bool,
+    pub cpu_vendor: String,
+    pub cpu_name: String,
+    pub cpu_family: u32,
+    pub cpu_model: u32,
+    pub cpu_stepping: u32,
+    pub pci: Vec<PciDevice>,
+    pub usb: Vec<(u16, u16)>, // idVendor, idProduct of attached USB devices
+}
+
+impl Profile {
+    /// id and display name of the CPU generation, None if unknown
+    /// the id is what the `cpu` lists of the quirk presets use
+    pub fn cpu_generation(&self) -> Option<(&'static str, &'static str)> {
+        match self.cpu_vendor.as_str() {
+            "GenuineIntel" if self.cpu_family == 6 => match self.cpu_model {
+                // Kaby Lake and Coffee Lake share models, newer steppings are Coffee Lake
+                0x8e | 0x9e if self.cpu_stepping <= 9 => Some(("kaby_lake", "Kaby Lake")),
+                0x8e | 0x9e => Some(("coffee_lake", "Coffee Lake")),
+                model => INTEL_GENERATIONS
+                    .iter()
+                    .find(|g| g.0.contains(&model))
+                    .map(|g| (g.1, g.2)),
+            },
+            "AuthenticAMD" => match self.cpu_family {
+                0x15 | 0x16 => Some(("amd_bulldozer", "AMD Bulldozer/Jaguar")),
+                0x17 | 0x19 | 0x1a => Some(("amd_zen", "AMD Ryzen/Threadripper")),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// kexts to start with for this machine and the reason for each, in load order
+    pub fn suggested_kexts(&self) -> Vec<(String, String)> {
+        let mut kexts: Vec<(String, String)> = vec![];
+        let mut add = |kext: &str, reason: String| {
+            if !kexts.iter().any(|k| k.0 == kext) {
+                kexts.push((kext.to_string(), reason));
+            }
+        };
+        add("Lilu.kext", "needed by most other kexts".to_string());
+        add("VirtualSMC.kext", "SMC emulation".to_string());
+        if self.cpu_vendor == "GenuineIntel" {
+            add("SMCProcessor.kext", "Intel CPU sensors".to_string());
+            if !self.laptop {
+                add("SMCSuperIO.kext", "fan speed sensors".to_string());
+            }
+        }
+        if self.laptop {
+            add("SMCBatteryManager.kext", "laptop battery".to_string());
+            add("ECEnabler.kext", "laptop battery status".to_string());
+            add(
+                "VoodooPS2Controller.kext",
+                "laptop keyboard and PS2 trackpad".to_string(),
+            );
+            add("BrightnessKeys.kext", "laptop brightness keys".to_string());
+            if self.sys_vendor.starts_with("Dell") {
+                add("SMCDellSensors.kext", "Dell fan sensors".to_string());
+            }
+        }
+        for dev in &self.pci {
+            let class = dev.class >> 8;
+            if let Some(rule) = PCI_RULES.iter().find(|r| {
+                (r.0 == 0 || r.0 == dev.vendor)
+                    && (r.1.is_empty() || r.1.contains(&dev.device))
+                    && r.2 == class
+            }) {
+                for kext in rule.3 {
+                    add(kext, format!("{} {}", dev.address, pci_name(dev)));
+                }
+            }
+            if self.laptop
+                && dev.vendor == VENDOR_INTEL
+                && [CLASS_SERIAL_OTHER, CLASS_SIGNAL_PROC].contains(&class)
+            {
+                add(
+                    "VoodooI2C.kext",
+                    format!("{} Intel serial IO, if the trackpad is I2C", dev.address),
+                );
+                add("VoodooI2CHID.kext", "I2C HID trackpad".to_string());
+            }
+        }
+        for (vendor, product) in &self.usb {
+            if let Some(rule) = USB_RULES.iter().find(|r| r.0 == *vendor) {
+                for kext in rule.1 {
+                    add(
+                        kext,
+                        format!("USB {:04x}:{:04x} bluetooth", vendor, product),
+                    );
+                }
+            }
+        }
+        if self.pci.iter().any(|d| d.class >> 8 == CLASS_USB) {
+            add(
+                "USBToolBox.kext",
+                "USB ports need to be mapped with a UTBMap.kext".to_string(),
+            );
+        }
+        kexts
+    }
+}
+
+/// read the machine details from the sysfs and procfs trees under `root`
+/// `root` is `/` for the running machine, or a folder holding a captured copy
+pub fn detect(root: &Path) -> Profile {
+    let read = |path: &str| {
+        fs::read_to_string(root.join(path))
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let mut profile = Profile {
+        sys_vendor: read("sys/class/dmi/id/sys_vendor"),
+        product_name: read("sys/class/dmi/id/product_name"),
+        board_name: read("sys/class/dmi/id/board_name"),
+        laptop: LAPTOP_CHASSIS
+            .contains(&read("sys/class/dmi/id/chassis_type").parse().unwrap_or(0)),
+        ..Default::default()
+    };
+
+    // values of the first processor are enough
+    for line in read("proc/cpuinfo").lines() {
+        let (key, val) = match line.split_once(':') {
+            Some((k, v)) => (k.trim(), v.trim()),
+            None => {
+                if line.trim().is_empty() && !profile.cpu_vendor.is_empty() {
+                    break;
+                }
+                continue;
+            }
+        };
+        match key {
+            "vendor_id" => profile.cpu_vendor = val.to_string(),
+            "model name" => profile.cpu_name = val.to_string(),
+            "cpu family" => profile.cpu_family = val.parse().unwrap_or(0),
+            "model" => profile.cpu_model = val.parse().unwrap_or(0),
+            "stepping" => profile.cpu_stepping = val.parse().unwrap_or(0),
+            _ => (),
+        }
+    }
+
+    for dev in sorted_dir(&root.join("sys/bus/pci/devices")) {
+        let read_hex = |file: &str| {
+            let s = fs::read_to_string(dev.join(file)).unwrap_or_default();
+            u32::from_str_radix(s.trim().trim_start_matches("0x"), 16).unwrap_or(0)
+        };
+        profile.pci.push(PciDevice {
+            address: dev.file_name().unwrap().to_string_lossy().to_string(),
+            vendor: read_hex("vendor") as u16,
+            device: read_hex("device") as u16,
+            class: read_hex("class"),
+        });
+    }
+
+    for dev in sorted_dir(&root.join("sys/bus/usb/devices")) {
+        let read_hex = |file: &str| {
+            let s = fs::read_to_string(dev.join(file)).ok()?;
+            u16::from_str_radix(s.trim(), 16).ok()
+        };
+        if let (Some(vendor), Some(product)) = (read_hex("idVendor"), read_hex("idProduct")) {
+            profile.usb.push((vendor, product));
+        }
+    }
+    profile
+}
+
+/// show the detected hardware and suggested kexts and quirk preset, then offer to
+/// add the missing kexts to Kernel > Add
+pub fn show_profile(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    let profile = detect(&resources.hw_root_path);
+    write!(
+        stdout,
+        "\r\n\x1b[2K\x1b[4mHardware\x1b[0m detected from {:?}\x1b[0K\r\n",
+        resources.hw_root_path
+    )
+    .unwrap();
+    if profile.cpu_vendor.is_empty() && profile.pci.is_empty() {
+        write!(
+            stdout,
+            "\x1b[31mERROR:\x1b[0m no cpuinfo or PCI devices found, detection needs Linux \
+             sysfs and procfs, or a captured copy given with the -w option\x1b[0K\r\n"
+        )
+        .unwrap();
+        return;
+    }
+    write!(
+        stdout,
+        "\x1b[7mmachine\x1b[0m  {} {} board {} {}\x1b[0K\r\n\
+         \x1b[7mCPU\x1b[0m      {} family {:#x} model {:#x} stepping {}\x1b[0K\r\n",
+        profile.sys_vendor,
+        profile.product_name,
+        profile.board_name,
+        if profile.laptop { "laptop" } else { "desktop" },
+        profile.cpu_name,
+        profile.cpu_family,
+        profile.cpu_model,
+        profile.cpu_stepping,
+    )
+    .unwrap();
+    for dev in &profile.pci {
+        let class = dev.class >> 8;
+        if [
+            CLASS_ETHERNET,
+            CLASS_WIRELESS,
+            CLASS_VGA,
+            CLASS_DISPLAY,
+            CLASS_AUDIO,
+            CLASS_NVME,
+            CLASS_USB,
+        ]
+        .contains(&class)
+        {
+            write!(
+                stdout,
+                "\x1b[7mPCI\x1b[0m      {} {:04x}:{:04x} {}\x1b[0K\r\n",
+                dev.address,
+                dev.vendor,
+                dev.device,
+                pci_name(dev)
+            )
+            .unwrap();
+        }
+    }
+    let presets = preset::matching(resources, &profile);
+    match profile.cpu_generation() {
+        Some((_, name)) if !presets.is_empty() => write!(
+            stdout,
+            "\r\n\x1b[32mCPU generation\x1b[0m {}, suggested quirk preset \x1b[32m{}\x1b[0m, \
+             apply it with the 'A' command\x1b[0K\r\n",
+            name,
+            presets.join(", ")
+        )
+        .unwrap(),
+        Some((_, name)) => write!(
+            stdout,
+            "\r\n\x1b[32mCPU generation\x1b[0m {}, \x1b[33mno quirk preset found\x1b[0m\x1b[0K\r\n",
+            name
+        )
+        .unwrap(),
+        None => write!(
+            stdout,
+            "\r\n\x1b[33mCPU generation unknown\x1b[0m, no quirk preset suggested\x1b[0K\r\n"
+        )
+        .unwrap(),
+    }
+
+    write!(stdout, "\r\n\x1b[32mSuggested kexts\x1b[0m\x1b[0K\r\n").unwrap();
+    let existing = kernel_add_bundles(resources);
+    let mut missing = vec![];
+    for (kext, reason) in profile.suggested_kexts() {
+        let status = if existing.contains(&kext) {
+            "\x1b[32min config\x1b[0m"
+        } else if resources.resource_list[&kext].is_object() {
+            missing.push(kext.to_owned());
+            "\x1b[33mmissing\x1b[0m  "
+        } else {
+            "\x1b[31munknown\x1b[0m  "
+        };
+        write!(stdout, "  {} {:<28} {}\x1b[0K\r\n", status, kext, reason).unwrap();
+    }
+    if missing.is_empty() {
+        return;
+    }
+    write!(
+        stdout,
+        "\r\n\x1b[4mPress\x1b[0m '\x1b[32ma\x1b[0m' to add the {} missing kexts to Kernel > Add, \
+         unknown kexts need to be put in the INPUT folder and added manually, \
+         any other key to continue\x1b[0K\r\n\x1b[2K",
+        missing.len()
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    if read_key().unwrap().0 != KeyCode::Char('a') {
+        return;
+    }
+    let template = match snapshot::sample_template(resources, "Kernel", "Add") {
+        Some(Value::Dictionary(d)) => d,
+        _ => return,
+    };
+    if let Some(Value::Array(add)) = resources
+        .config_plist
+        .as_dictionary_mut()
+        .and_then(|d| d.get_mut("Kernel"))
+        .and_then(|k| k.as_dictionary_mut())
+        .and_then(|k| k.get_mut("Add"))
+    {
+        for kext in &missing {
+            let mut item = template.clone();
+            item.insert("Arch".to_string(), Value::String("Any".to_string()));
+            item.insert("BundlePath".to_string(), Value::String(kext.to_owned()));
+            item.insert(
+                "ExecutablePath".to_string(),
+                Value::String(format!(
+                    "Contents/MacOS/{}",
+                    kext.split('.').next().unwrap()
+                )),
+            );
+            item.insert(
+                "PlistPath".to_string(),
+                Value::String("Contents/Info.plist".to_string()),
+            );
+            item.insert("Enabled".to_string(), Value::Boolean(true));
+            add.push(Value::Dictionary(item));
+            write!(
+                stdout,
+                "\x1b[7mAdded\x1b[0m Kernel->Add->{}\x1b[0K\r\n",
+                kext
+            )
+            .unwrap();
+        }
+    }
+    settings.modified = true;
+    write!(
+        stdout,
+        "\r\n\x1b[32mChecking\x1b[0m for missing requirements and wrong order\x1b[0K\r\n"
+    )
+    .unwrap();
+    let mut order_attempts = 0;
+    while !res::check_order(settings, resources, stdout, false) {
+        order_attempts += 1;
+        if order_attempts > 10 {
+            break;
+        }
+    }
+}
+
+/// BundlePath of every Kernel > Add entry
+fn kernel_add_bundles(resources: &Resources) -> Vec<String> {
+    resources
+        .config_plist
+        .as_dictionary()
+        .and_then(|d| d.get("Kernel"))
+        .and_then(|k| k.as_dictionary())
+        .and_then(|k| k.get("Add"))
+        .and_then(|a| a.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|k| k.as_dictionary())
+                .filter_map(|k| k.get("BundlePath").and_then(|b| b.as_string()))
+                .map(|b| b.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// short description of a PCI device from its vendor and class
+fn pci_name(dev: &PciDevice) -> String {
+    let vendor = match dev.vendor {
+        VENDOR_INTEL => "Intel",
+        VENDOR_AMD => "AMD",
+        VENDOR_NVIDIA => "NVIDIA",
+        VENDOR_REALTEK => "Realtek",
+        VENDOR_BROADCOM => "Broadcom",
+        VENDOR_ATHEROS => "Atheros",
+        _ => "",
+    };
+    let class = match dev.class >> 8 {
+        CLASS_ETHERNET => "ethernet".to_string(),
+        CLASS_WIRELESS => "wireless".to_string(),
+        CLASS_VGA | CLASS_DISPLAY => "graphics".to_string(),
+        CLASS_AUDIO => "audio".to_string(),
+        CLASS_NVME => "NVMe".to_string(),
+        CLASS_USB => match dev.class & 0xff {
+            0x00 => "UHCI USB".to_string(),
+            0x10 => "OHCI USB".to_string(),
+            0x20 => "EHCI USB".to_string(),
+            0x30 => "XHCI USB".to_string(),
+            _ => "USB".to_string(),
+        },
+        CLASS_SERIAL_OTHER | CLASS_SIGNAL_PROC => "serial IO".to_string(),
+        c => format!("class {:04x}", c),
+    };
+    format!("{} {}", vendor, class).trim().to_string()
+}
+
+fn sorted_dir(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(d) => d.filter_map(Result::ok).map(|e| e.path()).collect(),
+        Err(_) => vec![],
+    };
+    entries.sort();
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a Dell laptop with a Coffee Lake CPU, Intel ethernet, audio, XHCI and serial
+    /// IO controllers and an Intel bluetooth adapter
+    fn laptop_tree(root: &Path) {
+        let write = |path: &str, text: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("sys/class/dmi/id/sys_vendor", "Dell Inc.\n");
+        write("sys/class/dmi/id/product_name", "XPS 13 9380\n");
+        write("sys/class/dmi/id/chassis_type", "10\n");
+        write(
+            "proc/cpuinfo",
+            "processor\t: 0\nvendor_id\t: GenuineIntel\ncpu family\t: 6\nmodel\t\t: 142\n\
+             model name\t: Intel(R) Core(TM) i7-8565U CPU @ 1.80GHz\nstepping\t: 11\n\n\
+             processor\t: 1\nvendor_id\t: AuthenticAMD\n",
+        );
+        for (address, vendor, device, class) in [
+            ("0000:00:14.0", "0x8086", "0x9ded", "0x0c0330"),
+            ("0000:00:15.0", "0x8086", "0x9de8", "0x0c8000"),
+            ("0000:00:1f.3", "0x8086", "0x9dc8", "0x040380"),
+            ("0000:00:1f.6", "0x8086", "0x15be", "0x020000"),
+        ] {
+            write(&format!("sys/bus/pci/devices/{}/vendor", address), vendor);
+            write(&format!("sys/bus/pci/devices/{}/device", address), device);
+            write(&format!("sys/bus/pci/devices/{}/class", address), class);
+        }
+        write("sys/bus/usb/devices/1-10/idVendor", "8087\n");
+        write("sys/bus/usb/devices/1-10/idProduct", "0aaa\n");
+        write("sys/bus/usb/devices/usb1/idVendor", "1d6b\n");
+        write("sys/bus/usb/devices/usb1/idProduct", "0002\n");
+    }
+
+    #[test]
+    fn detects_hardware() {
+        let root = std::env::temp_dir().join(format!("octool_hw_{}", std::process::id()));
+        laptop_tree(&root);
+        let profile = detect(&root);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(profile.sys_vendor, "Dell Inc.");
+        assert!(profile.laptop);
+        assert_eq!(profile.cpu_vendor, "GenuineIntel");
+        assert_eq!(
+            (profile.cpu_family, profile.cpu_model, profile.cpu_stepping),
+            (6, 142, 11)
+        );
+        assert_eq!(
+            profile.cpu_generation(),
+            Some(("coffee_lake", "Coffee Lake"))
+        );
+        assert_eq!(profile.pci.len(), 4);
+        assert_eq!(profile.pci[0].class, 0x0c0330);
+        assert_eq!(pci_name(&profile.pci[0]), "Intel XHCI USB");
+        assert_eq!(profile.usb, vec![(0x8087, 0x0aaa), (0x1d6b, 0x0002)]);
+
+        let kexts: Vec<String> = profile.suggested_kexts().into_iter().map(|k| k.0).collect();
+        for kext in [
+            "Lilu.kext",
+            "SMCBatteryManager.kext",
+            "SMCDellSensors.kext",
+            "VoodooI2C.kext",
+            "AppleALC.kext",
+            "IntelMausi.kext",
+            "IntelBluetoothFirmware.kext",
+            "USBToolBox.kext",
+        ] {
+            assert!(kexts.contains(&kext.to_string()), "{} not suggested", kext);
+        }
+        assert!(!kexts.contains(&"SMCSuperIO.kext".to_string()));
+        assert_eq!(kexts[0], "Lilu.kext");
+    }
+
+    #[test]
+    fn empty_tree_finds_nothing() {
+        let profile = detect(Path::new("/nonexistent"));
+        assert!(!profile.laptop);
+        assert_eq!(profile.cpu_generation(), None);
+        assert!(profile.pci.is_empty());
+        assert_eq!(profile.suggested_kexts().len(), 2);
+    }
+
+    #[test]
+    fn names_cpu_generations() {
+        let cpu = |vendor: &str, family, model, stepping| Profile {
+            cpu_vendor: vendor.to_string(),
+            cpu_family: family,
+            cpu_model: model,
+            cpu_stepping: stepping,
+            ..Default::default()
+        };
+        assert_eq!(
+            cpu("GenuineIntel", 6, 0x9e, 9).cpu_generation().unwrap().0,
+            "kaby_lake"
+        );
+        assert_eq!(
+            cpu("GenuineIntel", 6, 0x3c, 3).cpu_generation().unwrap().0,
+            "haswell"
+        );
+        assert_eq!(cpu("GenuineIntel", 6, 0x01, 0).cpu_generation(), None);
+        assert_eq!(
+            cpu("AuthenticAMD", 0x19, 0x21, 0)
+                .cpu_generation()
+                .unwrap()
+                .0,
+            "amd_zen"
+        );
+    }
+}
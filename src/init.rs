@@ -5,8 +5,14 @@ use std::path::{Path, PathBuf};
 
 use plist::Value;
 
-use crate::edit::{find, Found};
+use crate::bulk::Marks;
+use crate::clipboard::Clipboard;
+use crate::differences;
+use crate::filter::ViewFilter;
+use crate::history::History;
+use crate::lint;
 use crate::res::{self, Resources};
+use crate::schema;
 
 use crossterm::terminal;
 
@@ -18,15 +24,15 @@ pub struct Manifest(pub usize, pub String);
 #[derive(Debug, Default)]
 pub struct Settings {
     pub config_file_name: String,          // name of config.plist
-    pub sec_num: [usize; 5],               // selected section for each depth
+    pub sec_num: Vec<usize>,               // selected section for each depth
     pub depth: usize,                      // depth of plist section we are looking at
-    pub sec_key: [String; 5],              // key of selected section
-    pub raw_sec_key: [String; 5],          // raw key of selected section, e.g. plain array #s
+    pub sec_key: Vec<String>,              // key of selected section
+    pub raw_sec_key: Vec<String>,          // raw key of selected section, e.g. plain array #s
     pub item_instructions: String,         // item instructions for display in header
     pub held_item: Option<Value>,          // last deleted or placed item value
     pub held_key: String,                  // last deleted or placed key
     pub live_value: String,                // current value of highlighted key
-    pub sec_length: [usize; 5],            // number of items in current section
+    pub sec_length: Vec<usize>,            // number of items in current section
     pub resource_sections: Vec<String>,    // concat name of sections that contain resources
     pub build_type: String,                // building release or debug version
     pub oc_build_version: String,          // version number of OpenCorePkg to use
@@ -41,20 +47,33 @@ pub struct Settings {
     pub octool_version: String,            // octool version being used
     pub show_info_url: bool,               // display full url link in the info screens
     pub inside_an_array: bool,             // true if current selection is inside an array
+    pub migrate_from: String,              // OpenCore version to migrate the config.plist from
+    pub history: History,                  // undo and redo states of the config.plist
+    pub clipboard: Clipboard,              // named registers and recently cut or copied items
+    pub marks: Marks,                      // array items marked for bulk actions
+    pub filter: ViewFilter,                // items shown in a long section
 }
 
 impl Settings {
     pub fn up(&mut self) {
-        if self.sec_num[self.depth] > 0 {
+        let start = self.sec_num[self.depth];
+        while self.sec_num[self.depth] > 0 {
             self.sec_num[self.depth] -= 1;
+            if !self.filter.hides(self.depth, self.sec_num[self.depth]) {
+                return;
+            }
         }
+        self.sec_num[self.depth] = start; // nothing shown above
     }
     pub fn down(&mut self) {
-        if self.sec_length[self.depth] > 0 {
-            if self.sec_num[self.depth] < self.sec_length[self.depth] - 1 {
-                self.sec_num[self.depth] += 1;
+        let start = self.sec_num[self.depth];
+        while self.sec_num[self.depth] + 1 < self.sec_length[self.depth] {
+            self.sec_num[self.depth] += 1;
+            if !self.filter.hides(self.depth, self.sec_num[self.depth]) {
+                return;
             }
         }
+        self.sec_num[self.depth] = start; // nothing shown below
     }
     pub fn left(&mut self) {
         if self.depth > 0 {
@@ -64,12 +83,29 @@ impl Settings {
         self.inside_an_array = false;
     }
     pub fn right(&mut self) {
-        if self.depth < 4 && self.can_expand {
+        if self.can_expand {
             self.depth += 1;
+            self.fit_depth();
             self.sec_num[self.depth] = 0;
         }
         self.inside_an_array = false;
     }
+    /// grow the per depth selection to hold the current depth and the one below it
+    pub fn fit_depth(&mut self) {
+        let len = self.depth + 2;
+        if self.sec_num.len() < len {
+            self.sec_num.resize(len, 0);
+        }
+        if self.sec_length.len() < len {
+            self.sec_length.resize(len, 0);
+        }
+        if self.sec_key.len() < len {
+            self.sec_key.resize(len, String::new());
+        }
+        if self.raw_sec_key.len() < len {
+            self.raw_sec_key.resize(len, String::new());
+        }
+    }
     pub fn add(&mut self) {
         self.sec_length[self.depth] += 1;
         self.modified = true;
@@ -319,6 +355,7 @@ pub fn init_oc_build(
     let sample_plist = &resources.open_core_source_path.join("Docs/Sample.plist");
     resources.sample_plist = Value::from_file(sample_plist)
         .expect(format!("Didn't find Sample.plist at {:?}", sample_plist).as_str());
+    resources.schema = schema::load(&resources.open_core_source_path.join("Docs"));
 
     write!(
         stdout,
@@ -369,6 +406,7 @@ pub fn init_plist(
     validate_plist(&config_plist, &resources, stdout)?;
 
     //finish configuring settings
+    settings.fit_depth();
     settings.config_file_name = config_plist.to_str().unwrap().to_owned();
     settings.sec_length[0] = resources.config_plist.as_dictionary().unwrap().keys().len();
     let mut found_key = false;
@@ -392,6 +430,19 @@ pub fn init_plist(
     Ok(())
 }
 
+/// the ocvalidate utility for this OS in the loaded OpenCore binaries
+pub fn ocvalidate_path(resources: &Resources) -> PathBuf {
+    resources
+        .open_core_binaries_path
+        .join("Utilities/ocvalidate")
+        .join(match std::env::consts::OS {
+            "macos" => "ocvalidate",
+            "windows" => "ocvalidate.exe",
+            "linux" => "ocvalidate.linux",
+            _ => "ocvalidate",
+        })
+}
+
 /// run loaded config.plist through the corresponding ocvalidate utility if it
 /// exists (no ocvalidate before oc 0.6.0, may be no ocvalidate available depending
 /// on what OS is currently being run)
@@ -401,15 +452,7 @@ pub fn validate_plist(
     stdout: &mut Stdout,
 ) -> Result<bool, Box<dyn Error>> {
     let mut config_okay = true;
-    let ocvalidate_bin = resources
-        .open_core_binaries_path
-        .join("Utilities/ocvalidate")
-        .join(match std::env::consts::OS {
-            "macos" => "ocvalidate",
-            "windows" => "ocvalidate.exe",
-            "linux" => "ocvalidate.linux",
-            _ => "ocvalidate",
-        });
+    let ocvalidate_bin = ocvalidate_path(resources);
     if ocvalidate_bin.exists() {
         let out = res::status(
             ocvalidate_bin.to_str().unwrap(),
@@ -435,26 +478,100 @@ pub fn validate_plist(
             ocvalidate_bin,
         )?;
     }
+
+    // octool's own rules, reported as warnings so they don't stop a build
+    if let Ok(config) = Value::from_file(config_plist) {
+        write!(stdout, "\x1B[32mChecking\x1B[0m config rules\r\n")?;
+        lint::write_rule_problems(&lint::check_rules(&config, resources), stdout);
+    }
     Ok(config_okay)
 }
 
-/// run through vec of "config_differences" from tool_config_files/octool_config.json
-/// if the current config.plist being worked on contains the field in the vec then
-/// it is most likely to be the correct version of OpenCore
-pub fn guess_version(resources: &Resources) -> (String, bool) {
-    let mut found = vec![Found::new()];
-    let config_differences: Vec<(String, String, String, String)> =
-        serde_json::from_value(resources.config_differences["config_differences"].clone()).unwrap();
-
-    let mut first_diff = true;
-    for (sec, sub, search, ver) in config_differences {
-        find(&search, &resources.config_plist, &mut found);
-        for result in &found {
-            if result.keys.contains(&sec) && result.keys.contains(&sub) {
-                return (ver.to_owned(), first_diff);
+/// OpenCore versions the loaded config.plist fits, worked out from the keys in it
+#[derive(Debug, Default)]
+pub struct VersionGuess {
+    pub best: String,            // most likely version, "" if no versions are known
+    pub latest: bool,            // true if the newest version is as likely as best
+    pub compatible: Vec<String>, // versions the keys fit, newest first
+    pub conflicts: usize,        // keys that don't fit even the compatible versions
+}
+
+/// score every OpenCorePkg version against the keys of the config.plist using the
+/// added and removed keys in config_differences.json
+/// a key the config.plist has that a version doesn't is a conflict, a key a version
+/// has that the config.plist is missing only makes that version less likely
+/// compatible versions are those with the fewest conflicts, the best guess is the
+/// oldest of them missing the fewest keys
+pub fn detect_versions(resources: &Resources) -> VersionGuess {
+    let mut versions: Vec<(String, Vec<u32>)> = vec![];
+    let mut i = 0;
+    while let Some(v) = resources.dortania["OpenCorePkg"]["versions"][i]["version"].as_str() {
+        if !versions.iter().any(|(s, _)| s == v) {
+            versions.push((v.to_owned(), lint::version_nums(v)));
+        }
+        i += 1;
+    }
+    versions.sort_by(|a, b| b.1.cmp(&a.1));
+
+    // added (true) and removed (false) events of each key, oldest first
+    let mut events: HashMap<[String; 3], Vec<(Vec<u32>, bool)>> = HashMap::new();
+    for (list, added) in [("config_differences", true), ("config_removals", false)] {
+        let entries: Vec<(String, String, String, String)> =
+            serde_json::from_value(resources.config_differences[list].clone()).unwrap_or_default();
+        for (sec, sub, key, ver) in entries {
+            events
+                .entry([sec, sub, key])
+                .or_default()
+                .push((lint::version_nums(&ver), added));
+        }
+    }
+    for e in events.values_mut() {
+        e.sort();
+    }
+
+    let mut config_keys = vec![];
+    differences::key_paths(&resources.config_plist, &mut vec![], &mut config_keys);
+
+    // (conflicts, missing keys) for each version
+    let scores: Vec<(usize, usize)> = versions
+        .iter()
+        .map(|(_, nums)| {
+            let mut score = (0, 0);
+            for (key, e) in &events {
+                let exists = match e.iter().rev().find(|(v, _)| v <= nums) {
+                    Some((_, added)) => *added,
+                    None => !e[0].1, // removed first, so it was there from the start
+                };
+                match (config_keys.contains(key), exists) {
+                    (true, false) => score.0 += 1,
+                    (false, true) => score.1 += 1,
+                    _ => (),
+                }
+            }
+            score
+        })
+        .collect();
+
+    let mut guess = VersionGuess::default();
+    let conflicts = match scores.iter().map(|s| s.0).min() {
+        Some(c) => c,
+        None => return guess,
+    };
+    guess.conflicts = conflicts;
+    let fewest_missing = scores
+        .iter()
+        .filter(|s| s.0 == conflicts)
+        .map(|s| s.1)
+        .min()
+        .unwrap_or(0);
+    for ((ver, _), score) in versions.iter().zip(&scores) {
+        if score.0 == conflicts {
+            guess.compatible.push(ver.to_owned());
+            if score.1 == fewest_missing {
+                guess.best = ver.to_owned(); // versions are newest first, keep the oldest
             }
         }
-        first_diff = false;
     }
-    ("".to_string(), false)
+    guess.latest = scores[0] == (conflicts, fewest_missing);
+    guess
 }
@@ -0,0 +1,747 @@
+use crate::edit::read_key;
+use crate::init::Settings;
+use crate::res::Resources;
+
+use crossterm::event::KeyCode;
+use plist::{Dictionary, Value};
+
+use std::io::{Stdout, Write};
+use std::path::PathBuf;
+
+/// sections checked for duplicates and the fields that identify an entry
+/// scope fields such as Identifier, Arch and MinKernel/MaxKernel are included so the
+/// same kext or patch aimed at different targets or kernel ranges is not reported
+const DUPLICATE_SECTIONS: &[(&str, &str, &[&str])] = &[
+    ("ACPI", "Add", &["Path"]),
+    (
+        "Kernel",
+        "Add",
+        &["BundlePath", "Arch", "MinKernel", "MaxKernel"],
+    ),
+    ("UEFI", "Drivers", &["Path"]),
+    ("Misc", "Tools", &["Path"]),
+    (
+        "Kernel",
+        "Block",
+        &["Identifier", "Arch", "MinKernel", "MaxKernel"],
+    ),
+    (
+        "Kernel",
+        "Force",
+        &["Identifier", "BundlePath", "Arch", "MinKernel", "MaxKernel"],
+    ),
+    (
+        "ACPI",
+        "Patch",
+        &[
+            "TableSignature",
+            "OemTableId",
+            "TableLength",
+            "Base",
+            "BaseSkip",
+            "Find",
+            "Mask",
+            "Replace",
+            "ReplaceMask",
+            "Skip",
+            "Count",
+            "Limit",
+        ],
+    ),
+    (
+        "Booter",
+        "Patch",
+        &[
+            "Identifier",
+            "Arch",
+            "Find",
+            "Mask",
+            "Replace",
+            "ReplaceMask",
+            "Skip",
+            "Count",
+            "Limit",
+        ],
+    ),
+    (
+        "Kernel",
+        "Patch",
+        &[
+            "Identifier",
+            "Arch",
+            "MinKernel",
+            "MaxKernel",
+            "Base",
+            "Find",
+            "Mask",
+            "Replace",
+            "ReplaceMask",
+            "Skip",
+            "Count",
+            "Limit",
+        ],
+    ),
+];
+
+/// sections holding binary patches
+const PATCH_SECTIONS: &[&str] = &["ACPI", "Booter", "Kernel"];
+
+/// GUID of the Apple NVRAM variables, boot-args lives here
+const APPLE_NVRAM_GUID: &str = "7C436110-AB2A-4BBB-A880-FE41995C9F82";
+
+/// SecureBootModel values and the first macOS version each model supports
+const SECURE_BOOT_MODELS: &[(&str, &str)] = &[
+    ("j137", "10.13.2"),
+    ("j680", "10.13.6"),
+    ("j132", "10.13.6"),
+    ("j174", "10.14"),
+    ("j140k", "10.14.1"),
+    ("j780", "10.14.5"),
+    ("j213", "10.14.5"),
+    ("j140a", "10.14.5"),
+    ("j152f", "10.15.1"),
+    ("j160", "10.15.1"),
+    ("j230k", "10.15.3"),
+    ("j214k", "10.15.4"),
+    ("j223", "10.15.4"),
+    ("j215", "10.15.5"),
+    ("j185", "10.15.6"),
+    ("j185f", "10.15.6"),
+    ("x86legacy", "11.0.1"),
+];
+
+/// boot-args that turn off a kext, and the prefixes of the boot-args that then do nothing
+const BOOT_ARG_OFF_SWITCHES: &[(&str, &[&str])] = &[
+    (
+        "-liluoff",
+        &[
+            "-lilu", "-weg", "igfx", "agdpmod", "alcid", "-alc", "-brcmfx",
+        ],
+    ),
+    (
+        "-wegoff",
+        &["-weg", "igfx", "agdpmod", "shikigva", "unfairgva"],
+    ),
+    ("-alcoff", &["alcid", "-alcbeta"]),
+];
+
+/// a config rule, `check` returns the key path and description of each problem
+struct Rule {
+    id: &'static str, // used to disable the rule in octool_config.json
+    check: fn(&Value, &Resources) -> Vec<(String, String)>,
+}
+
+/// checks of common mistakes that ocvalidate does not catch, or that are needed
+/// when ocvalidate is not available
+const RULES: &[Rule] = &[
+    Rule {
+        id: "required_drivers",
+        check: rule_required_drivers,
+    },
+    Rule {
+        id: "secure_boot_model",
+        check: rule_secure_boot_model,
+    },
+    Rule {
+        id: "vault",
+        check: rule_vault,
+    },
+    Rule {
+        id: "boot_args",
+        check: rule_boot_args,
+    },
+    Rule {
+        id: "nvram_delete",
+        check: rule_nvram_delete,
+    },
+];
+
+/// an enabled entry that is the same as an earlier enabled entry in its section
+#[derive(Debug)]
+pub struct Duplicate {
+    pub sec: String,  // section e.g. Kernel
+    pub sub: String,  // sub section e.g. Add
+    pub first: usize, // index of the first, kept, entry
+    pub dup: usize,   // index of the duplicate entry
+    pub name: String, // display name of the entry
+}
+
+/// a Patch entry with one or more problems
+#[derive(Debug)]
+pub struct PatchProblem {
+    pub sec: String,           // section e.g. Kernel
+    pub index: usize,          // index of the entry in the Patch array
+    pub name: String,          // display name of the entry
+    pub problems: Vec<String>, // description of each problem
+}
+
+/// a problem found by one of the config rules
+#[derive(Debug)]
+pub struct RuleProblem {
+    pub rule: &'static str, // id of the rule that found it
+    pub path: String,       // key path of the problem e.g. NVRAM->Delete
+    pub message: String,
+}
+
+/// find every enabled entry that duplicates an earlier enabled entry in the
+/// resource and patch sections of the `config` plist
+pub fn find_duplicates(config: &Value) -> Vec<Duplicate> {
+    let mut dupes = vec![];
+    for (sec, sub, fields) in DUPLICATE_SECTIONS {
+        let items = match section(config, sec, sub) {
+            Some(a) => a,
+            None => continue,
+        };
+        let mut seen: Vec<(Vec<Value>, usize)> = vec![];
+        for (i, item) in items.iter().enumerate() {
+            if !is_enabled(item) {
+                continue;
+            }
+            let id: Vec<Value> = match item {
+                Value::Dictionary(d) => fields
+                    .iter()
+                    .map(|f| d.get(f).cloned().unwrap_or(Value::Boolean(false)))
+                    .collect(),
+                Value::String(s) => vec![Value::String(s.to_owned())],
+                _ => continue,
+            };
+            match seen.iter().find(|s| s.0 == id) {
+                Some((_, first)) => dupes.push(Duplicate {
+                    sec: sec.to_string(),
+                    sub: sub.to_string(),
+                    first: *first,
+                    dup: i,
+                    name: entry_name(item, i),
+                }),
+                None => seen.push((id, i)),
+            }
+        }
+    }
+    dupes
+}
+
+/// disable the duplicate entries, keeping the first of each, returns number disabled
+pub fn disable_duplicates(config: &mut Value, dupes: &[Duplicate]) -> usize {
+    let mut count = 0;
+    for dupe in dupes {
+        let item = match config
+            .as_dictionary_mut()
+            .and_then(|d| d.get_mut(&dupe.sec))
+            .and_then(|s| s.as_dictionary_mut())
+            .and_then(|s| s.get_mut(&dupe.sub))
+            .and_then(|a| a.as_array_mut())
+            .and_then(|a| a.get_mut(dupe.dup))
+        {
+            Some(i) => i,
+            None => continue,
+        };
+        match item {
+            Value::Dictionary(d) => {
+                d.insert("Enabled".to_string(), Value::Boolean(false));
+                count += 1;
+            }
+            Value::String(s) => {
+                s.insert(0, '#');
+                count += 1;
+            }
+            _ => (),
+        }
+    }
+    count
+}
+
+/// check a single `sec` > Patch entry, returns a description of each problem found
+pub fn check_patch(sec: &str, patch: &Dictionary) -> Vec<String> {
+    let mut problems = vec![];
+    let data_len = |k: &str| match patch.get(k) {
+        Some(Value::Data(d)) => d.len(),
+        _ => 0,
+    };
+    let string = |k: &str| patch.get(k).and_then(|s| s.as_string()).unwrap_or("");
+    let (find, replace) = (data_len("Find"), data_len("Replace"));
+    let (mask, replace_mask) = (data_len("Mask"), data_len("ReplaceMask"));
+    let base = string("Base");
+
+    if find == 0 {
+        if base.is_empty() {
+            if sec == "Booter" {
+                problems.push("Find is empty".to_string());
+            } else {
+                problems.push("Find and Base are both empty".to_string());
+            }
+        } else {
+            // patching at Base alone, Replace is written at the Base location
+            if replace == 0 {
+                problems.push("Replace is empty".to_string());
+            }
+            if mask > 0 {
+                problems.push("Mask is set but Find is empty".to_string());
+            }
+        }
+    } else if find != replace {
+        problems.push(format!(
+            "Find is {} bytes but Replace is {} bytes",
+            find, replace
+        ));
+    }
+    if mask > 0 && mask != find {
+        problems.push(format!("Mask is {} bytes but Find is {} bytes", mask, find));
+    }
+    if replace_mask > 0 && replace_mask != replace {
+        problems.push(format!(
+            "ReplaceMask is {} bytes but Replace is {} bytes",
+            replace_mask, replace
+        ));
+    }
+
+    let identifier = string("Identifier");
+    match sec {
+        "Kernel" if identifier != "kernel" && !is_bundle_id(identifier) => {
+            problems.push(format!(
+                "Identifier '{}' should be 'kernel' or a kext bundle identifier",
+                identifier
+            ));
+        }
+        "Booter"
+            if identifier != "Apple"
+                && identifier != "Any"
+                && !identifier.to_lowercase().ends_with(".efi") =>
+        {
+            problems.push(format!(
+                "Identifier '{}' should be 'Apple', 'Any' or an .efi file name",
+                identifier
+            ));
+        }
+        "ACPI" => {
+            for (k, len) in [("TableSignature", 4), ("OemTableId", 8)] {
+                let l = data_len(k);
+                if l != 0 && l != len {
+                    problems.push(format!("{} is {} bytes, should be 0 or {}", k, l, len));
+                }
+            }
+        }
+        _ => (),
+    }
+
+    if string("Comment").trim().is_empty() {
+        problems.push("Comment is empty".to_string());
+    }
+    problems
+}
+
+/// check every entry of the ACPI, Booter and Kernel > Patch arrays
+pub fn find_patch_problems(config: &Value) -> Vec<PatchProblem> {
+    let mut found = vec![];
+    for sec in PATCH_SECTIONS {
+        let items = match section(config, sec, "Patch") {
+            Some(a) => a,
+            None => continue,
+        };
+        for (i, item) in items.iter().enumerate() {
+            if let Value::Dictionary(d) = item {
+                let problems = check_patch(sec, d);
+                if !problems.is_empty() {
+                    found.push(PatchProblem {
+                        sec: sec.to_string(),
+                        index: i,
+                        name: entry_name(item, i),
+                        problems,
+                    });
+                }
+            }
+        }
+    }
+    found
+}
+
+/// run every rule not listed in the `lint_disabled_rules` of octool_config.json
+pub fn check_rules(config: &Value, resources: &Resources) -> Vec<RuleProblem> {
+    let disabled: Vec<String> =
+        serde_json::from_value(resources.octool_config["lint_disabled_rules"].clone())
+            .unwrap_or_default();
+    let mut found = vec![];
+    for rule in RULES.iter().filter(|r| !disabled.iter().any(|d| d == r.id)) {
+        for (path, message) in (rule.check)(config, resources) {
+            found.push(RuleProblem {
+                rule: rule.id,
+                path,
+                message,
+            });
+        }
+    }
+    found
+}
+
+/// show the duplicate report in the editor and offer to disable the duplicates
+pub fn show_report(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    write!(
+        stdout,
+        "\r\n\x1b[2K\x1b[32mChecking\x1b[0m for duplicate entries\x1b[0K\r\n"
+    )
+    .unwrap();
+    let dupes = find_duplicates(&resources.config_plist);
+    write_duplicates(&dupes, stdout);
+    write!(stdout, "\x1b[32mChecking\x1b[0m Patch entries\x1b[0K\r\n").unwrap();
+    write_patch_problems(&find_patch_problems(&resources.config_plist), stdout);
+    write!(stdout, "\x1b[32mChecking\x1b[0m config rules\x1b[0K\r\n").unwrap();
+    write_rule_problems(&check_rules(&resources.config_plist, resources), stdout);
+    if dupes.is_empty() {
+        return;
+    }
+    write!(
+        stdout,
+        "\r\n\x1b[4mPress\x1b[0m '\x1b[32mD\x1b[0m' to disable all but the first of each \
+         duplicate, any other key to cancel\x1b[0K\r\n\x1b[2K"
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    if read_key().unwrap().0 == KeyCode::Char('D') {
+        let count = disable_duplicates(&mut resources.config_plist, &dupes);
+        if count > 0 {
+            settings.modified = true;
+        }
+        write!(
+            stdout,
+            "\x1b[32mDisabled\x1b[0m {} duplicate entries\x1b[0K\r\n\x1b[2K",
+            count
+        )
+        .unwrap();
+    }
+}
+
+/// lint the loaded config without starting the editor, if `fix` is true disable
+/// the duplicates and save the result as a modified_ copy in the INPUT folder
+/// returns true if no problems were found
+pub fn run_headless(
+    settings: &Settings,
+    resources: &mut Resources,
+    fix: bool,
+    stdout: &mut Stdout,
+) -> bool {
+    write!(
+        stdout,
+        "\r\n\x1b[32mLinting\x1b[0m {}\r\n",
+        settings.config_file_name
+    )
+    .unwrap();
+    let dupes = find_duplicates(&resources.config_plist);
+    write_duplicates(&dupes, stdout);
+    let patch_problems = find_patch_problems(&resources.config_plist);
+    write_patch_problems(&patch_problems, stdout);
+    let rule_problems = check_rules(&resources.config_plist, resources);
+    write_rule_problems(&rule_problems, stdout);
+    if fix && !dupes.is_empty() {
+        let count = disable_duplicates(&mut resources.config_plist, &dupes);
+        let mut config_file = PathBuf::from(&settings.config_file_name)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        if !config_file.starts_with("modified_") {
+            config_file.insert_str(0, "modified_");
+        }
+        let save_path = resources.input_dir_path.join(&config_file);
+        match resources.config_plist.to_file_xml(&save_path) {
+            Ok(_) => write!(
+                stdout,
+                "\x1b[32mDisabled\x1b[0m {} duplicate entries, saved to {:?}\r\n",
+                count, save_path
+            )
+            .unwrap(),
+            Err(e) => write!(
+                stdout,
+                "\x1b[31mERROR:\x1b[0m could not save {:?}: {}\r\n",
+                save_path, e
+            )
+            .unwrap(),
+        }
+    }
+    stdout.flush().unwrap();
+    dupes.is_empty() && patch_problems.is_empty() && rule_problems.is_empty()
+}
+
+fn write_duplicates(dupes: &[Duplicate], stdout: &mut Stdout) {
+    if dupes.is_empty() {
+        write!(
+            stdout,
+            "\x1b[32mNo duplicate entries found\x1b[0m\x1b[0K\r\n"
+        )
+        .unwrap();
+    }
+    for dupe in dupes {
+        write!(
+            stdout,
+            "\x1b[33mDuplicate\x1b[0m {}->{}->{} {} \x1b[33msame as\x1b[0m {}->{}->{}\x1b[0K\r\n",
+            dupe.sec, dupe.sub, dupe.dup, dupe.name, dupe.sec, dupe.sub, dupe.first
+        )
+        .unwrap();
+    }
+}
+
+fn write_patch_problems(found: &[PatchProblem], stdout: &mut Stdout) {
+    if found.is_empty() {
+        write!(stdout, "\x1b[32mNo Patch problems found\x1b[0m\x1b[0K\r\n").unwrap();
+    }
+    for patch in found {
+        for problem in &patch.problems {
+            write!(
+                stdout,
+                "\x1b[33mPatch\x1b[0m {}->Patch->{} {} \x1b[33m{}\x1b[0m\x1b[0K\r\n",
+                patch.sec, patch.index, patch.name, problem
+            )
+            .unwrap();
+        }
+    }
+}
+
+pub fn write_rule_problems(found: &[RuleProblem], stdout: &mut Stdout) {
+    if found.is_empty() {
+        write!(stdout, "\x1b[32mNo rule problems found\x1b[0m\x1b[0K\r\n").unwrap();
+    }
+    for problem in found {
+        write!(
+            stdout,
+            "\x1b[33m{}\x1b[0m {} \x1b[33m{}\x1b[0m\x1b[0K\r\n",
+            problem.rule, problem.path, problem.message
+        )
+        .unwrap();
+    }
+}
+
+/// OpenRuntime is needed by the Booter quirks, and OpenCanopy by the External picker
+fn rule_required_drivers(config: &Value, _: &Resources) -> Vec<(String, String)> {
+    let mut found = vec![];
+    let drivers: Vec<String> = section(config, "UEFI", "Drivers")
+        .map(|a| {
+            a.iter()
+                .filter(|d| is_enabled(d))
+                .map(|d| entry_name(d, 0).to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut required = vec!["OpenRuntime.efi"];
+    if string_at(config, &["Misc", "Boot", "PickerMode"]) == Some("External") {
+        required.push("OpenCanopy.efi");
+    }
+    for driver in required {
+        if !drivers.contains(&driver.to_lowercase()) {
+            found.push((
+                "UEFI->Drivers".to_string(),
+                format!("{} is missing or not enabled", driver),
+            ));
+        }
+    }
+    found
+}
+
+/// the SecureBootModel must support the macOS version set as `lint_target_macos`,
+/// and Apple Secure Boot does not allow loading unsigned images
+fn rule_secure_boot_model(config: &Value, resources: &Resources) -> Vec<(String, String)> {
+    let mut found = vec![];
+    let path = "Misc->Security->SecureBootModel".to_string();
+    let model = match string_at(config, &["Misc", "Security", "SecureBootModel"]) {
+        Some(m) if m != "Disabled" => m,
+        _ => return found,
+    };
+    if string_at(config, &["Misc", "Security", "DmgLoading"]) == Some("Any") {
+        found.push((
+            "Misc->Security->DmgLoading".to_string(),
+            format!("Any can not be used when SecureBootModel is {}", model),
+        ));
+    }
+    let target = resources.octool_config["lint_target_macos"]
+        .as_str()
+        .unwrap_or("");
+    if target.is_empty() {
+        return found;
+    }
+    if let Some((_, min)) = SECURE_BOOT_MODELS.iter().find(|m| m.0 == model) {
+        if version_nums(target) < version_nums(min) {
+            found.push((
+                path,
+                format!(
+                    "{} needs macOS {} or newer, target macOS is {}",
+                    model, min, target
+                ),
+            ));
+        }
+    }
+    found
+}
+
+/// a Vault other than Optional needs the vault files octool's build makes, which it
+/// can only do on macOS, see build::build_output
+fn rule_vault(config: &Value, _: &Resources) -> Vec<(String, String)> {
+    let vault = string_at(config, &["Misc", "Security", "Vault"]).unwrap_or("Optional");
+    match vault_problem(vault, std::env::consts::OS) {
+        Some(problem) => vec![("Misc->Security->Vault".to_string(), problem)],
+        None => vec![],
+    }
+}
+
+/// why a build on `os` can't make the files `vault` needs, None if it can
+fn vault_problem(vault: &str, os: &str) -> Option<String> {
+    if os == "macos" || !["Basic", "Secure"].contains(&vault) {
+        return None;
+    }
+    Some(format!(
+        "{} needs vault files that octool can only build on macOS, build the EFI on macOS \
+         or set Vault to Optional",
+        vault
+    ))
+}
+
+/// boot-args repeated with different values, or that are turned off by another arg
+fn rule_boot_args(config: &Value, _: &Resources) -> Vec<(String, String)> {
+    let mut found = vec![];
+    let path = format!("NVRAM->Add->{}->boot-args", APPLE_NVRAM_GUID);
+    let boot_args = match string_at(config, &["NVRAM", "Add", APPLE_NVRAM_GUID, "boot-args"]) {
+        Some(b) => b,
+        None => return found,
+    };
+    let args: Vec<&str> = boot_args.split_whitespace().collect();
+    for (i, arg) in args.iter().enumerate() {
+        let name = arg.split('=').next().unwrap();
+        if let Some(earlier) = args[..i]
+            .iter()
+            .find(|a| a.split('=').next().unwrap() == name)
+        {
+            found.push((
+                path.to_owned(),
+                if earlier == arg {
+                    format!("{} is set twice", arg)
+                } else {
+                    format!("{} conflicts with {}", arg, earlier)
+                },
+            ));
+        }
+    }
+    for (off, affected) in BOOT_ARG_OFF_SWITCHES {
+        if !args.contains(off) {
+            continue;
+        }
+        for arg in args.iter().filter(|a| a != &off) {
+            if affected.iter().any(|p| arg.starts_with(p)) {
+                found.push((
+                    path.to_owned(),
+                    format!("{} has no effect with {}", arg, off),
+                ));
+            }
+        }
+    }
+    found
+}
+
+/// existing NVRAM variables are only replaced if they are also listed in NVRAM > Delete
+fn rule_nvram_delete(config: &Value, _: &Resources) -> Vec<(String, String)> {
+    let mut found = vec![];
+    let nvram = match config
+        .as_dictionary()
+        .and_then(|d| d.get("NVRAM"))
+        .and_then(|n| n.as_dictionary())
+    {
+        Some(n) => n,
+        None => return found,
+    };
+    let add = match nvram.get("Add").and_then(|a| a.as_dictionary()) {
+        Some(a) => a,
+        None => return found,
+    };
+    let delete = nvram.get("Delete").and_then(|d| d.as_dictionary());
+    for (guid, vars) in add {
+        if guid.starts_with('#') {
+            continue;
+        }
+        let deleted: Vec<&str> = delete
+            .and_then(|d| d.get(guid))
+            .and_then(|d| d.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_string()).collect())
+            .unwrap_or_default();
+        for key in vars.as_dictionary().map(|v| v.keys()).into_iter().flatten() {
+            if !key.starts_with('#') && !deleted.contains(&key.as_str()) {
+                found.push((
+                    format!("NVRAM->Add->{}->{}", guid, key),
+                    "not listed in NVRAM->Delete, an existing value will not be replaced"
+                        .to_string(),
+                ));
+            }
+        }
+    }
+    found
+}
+
+/// string at the dictionary key `path` of the config
+fn string_at<'a>(config: &'a Value, path: &[&str]) -> Option<&'a str> {
+    let mut val = config;
+    for key in path {
+        val = val.as_dictionary()?.get(key)?;
+    }
+    val.as_string()
+}
+
+/// numbers of a dotted version string, e.g. 10.15.6 is [10, 15, 6]
+pub fn version_nums(ver: &str) -> Vec<u32> {
+    ver.split('.').map(|n| n.parse().unwrap_or(0)).collect()
+}
+
+/// reverse DNS style identifier such as `com.apple.driver.AppleHDA`
+fn is_bundle_id(id: &str) -> bool {
+    id.contains('.')
+        && !id.starts_with('.')
+        && !id.ends_with('.')
+        && !id.contains("..")
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+}
+
+fn section<'a>(config: &'a Value, sec: &str, sub: &str) -> Option<&'a Vec<Value>> {
+    config
+        .as_dictionary()?
+        .get(sec)?
+        .as_dictionary()?
+        .get(sub)?
+        .as_array()
+}
+
+pub fn is_enabled(item: &Value) -> bool {
+    match item {
+        Value::Dictionary(d) => d
+            .get("Enabled")
+            .and_then(|e| e.as_boolean())
+            .unwrap_or(true),
+        Value::String(s) => !s.starts_with('#'),
+        _ => false,
+    }
+}
+
+/// name used for an entry in reports, same fields the editor uses for array keys
+fn entry_name(item: &Value, i: usize) -> String {
+    if let Value::Dictionary(d) = item {
+        for k in ["Path", "BundlePath", "Identifier", "Comment"] {
+            if let Some(Value::String(s)) = d.get(k) {
+                if !s.is_empty() {
+                    return s.to_owned();
+                }
+            }
+        }
+    } else if let Value::String(s) = item {
+        return s.to_owned();
+    }
+    i.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vault_only_warns_where_it_can_not_be_built() {
+        for vault in ["Basic", "Secure"] {
+            assert_eq!(vault_problem(vault, "macos"), None);
+            assert!(vault_problem(vault, "linux").unwrap().starts_with(vault));
+            assert!(vault_problem(vault, "windows").is_some());
+        }
+        assert_eq!(vault_problem("Optional", "linux"), None);
+    }
+}
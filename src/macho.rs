@@ -0,0 +1,358 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const FAT_MAGIC: u32 = 0xcafebabe;
+const FAT_MAGIC_64: u32 = 0xcafebabf;
+const MH_MAGIC: u32 = 0xfeedface;
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const MH_CIGAM: u32 = 0xcefaedfe;
+const MH_CIGAM_64: u32 = 0xcffaedfe;
+
+const LC_UUID: u32 = 0x1b;
+const LC_VERSION_MIN_MACOSX: u32 = 0x24;
+const LC_BUILD_VERSION: u32 = 0x32;
+
+const CPU_TYPE_X86: u32 = 7;
+const CPU_TYPE_X86_64: u32 = 0x0100_0007;
+const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+
+/// info from a single architecture of a Mach-O executable
+#[derive(Debug, Default)]
+pub struct Slice {
+    pub arch: String,                    // i386, x86_64, arm64, etc.
+    pub min_os: Option<(u32, u32, u32)>, // minimum macOS from load commands
+    pub uuid: Option<String>,            // LC_UUID of the slice
+}
+
+impl Slice {
+    pub fn min_os_string(&self) -> String {
+        match self.min_os {
+            Some((ma, mi, pa)) => format!("{}.{}.{}", ma, mi, pa),
+            None => "unknown".to_string(),
+        }
+    }
+}
+
+/// read the executable at `path` and return the info of each slice it contains
+pub fn inspect(path: &Path) -> Result<Vec<Slice>, Box<dyn Error>> {
+    let data = fs::read(path)?;
+    parse(&data)
+}
+
+/// parse a fat or thin Mach-O binary
+pub fn parse(data: &[u8]) -> Result<Vec<Slice>, Box<dyn Error>> {
+    let magic = read_u32(data, 0, true)?;
+    match magic {
+        FAT_MAGIC | FAT_MAGIC_64 => {
+            let count = read_u32(data, 4, true)? as usize;
+            let entry_size = if magic == FAT_MAGIC { 20 } else { 32 };
+            let mut slices = vec![];
+            for i in 0..count {
+                let entry = 8 + i * entry_size;
+                let offset = if magic == FAT_MAGIC {
+                    read_u32(data, entry + 8, true)? as usize
+                } else {
+                    read_u64(data, entry + 8, true)? as usize
+                };
+                if offset >= data.len() {
+                    return Err(format!("fat arch {} offset outside of file", i).into());
+                }
+                slices.push(parse_thin(&data[offset..])?);
+            }
+            Ok(slices)
+        }
+        _ => Ok(vec![parse_thin(data)?]),
+    }
+}
+
+/// byte range of the `arch` slice inside a fat binary, the whole file for a thin
+/// binary, None if a fat binary has no slice for `arch`
+pub fn slice_range(data: &[u8], arch: &str) -> Option<std::ops::Range<usize>> {
+    let magic = read_u32(data, 0, true).ok()?;
+    if magic != FAT_MAGIC && magic != FAT_MAGIC_64 {
+        return Some(0..data.len());
+    }
+    let count = read_u32(data, 4, true).ok()? as usize;
+    for i in 0..count {
+        let (cpu_type, offset, size) = if magic == FAT_MAGIC {
+            let entry = 8 + i * 20;
+            (
+                read_u32(data, entry, true).ok()?,
+                read_u32(data, entry + 8, true).ok()? as usize,
+                read_u32(data, entry + 12, true).ok()? as usize,
+            )
+        } else {
+            let entry = 8 + i * 32;
+            (
+                read_u32(data, entry, true).ok()?,
+                read_u64(data, entry + 8, true).ok()? as usize,
+                read_u64(data, entry + 16, true).ok()? as usize,
+            )
+        };
+        if arch_name(cpu_type) == arch {
+            return Some(offset..(offset + size).min(data.len()));
+        }
+    }
+    None
+}
+
+fn parse_thin(data: &[u8]) -> Result<Slice, Box<dyn Error>> {
+    let (big_endian, header_size) = match read_u32(data, 0, true)? {
+        MH_MAGIC => (true, 28),
+        MH_MAGIC_64 => (true, 32),
+        MH_CIGAM => (false, 28),
+        MH_CIGAM_64 => (false, 32),
+        _ => return Err("not a Mach-O file".into()),
+    };
+    let mut slice = Slice {
+        arch: arch_name(read_u32(data, 4, big_endian)?),
+        ..Default::default()
+    };
+    let ncmds = read_u32(data, 16, big_endian)?;
+    let mut pos = header_size;
+    for _ in 0..ncmds {
+        let cmd = read_u32(data, pos, big_endian)?;
+        let cmd_size = read_u32(data, pos + 4, big_endian)? as usize;
+        match cmd {
+            LC_UUID => {
+                let uuid = data
+                    .get(pos + 8..pos + 24)
+                    .ok_or("truncated LC_UUID command")?;
+                slice.uuid = Some(format!(
+                    "{}-{}-{}-{}-{}",
+                    hex::encode_upper(&uuid[0..4]),
+                    hex::encode_upper(&uuid[4..6]),
+                    hex::encode_upper(&uuid[6..8]),
+                    hex::encode_upper(&uuid[8..10]),
+                    hex::encode_upper(&uuid[10..16]),
+                ));
+            }
+            LC_VERSION_MIN_MACOSX => {
+                slice.min_os = Some(unpack_version(read_u32(data, pos + 8, big_endian)?));
+            }
+            LC_BUILD_VERSION => {
+                slice.min_os = Some(unpack_version(read_u32(data, pos + 12, big_endian)?));
+            }
+            _ => (),
+        }
+        if cmd_size == 0 {
+            break;
+        }
+        pos += cmd_size;
+    }
+    Ok(slice)
+}
+
+fn arch_name(cpu_type: u32) -> String {
+    match cpu_type {
+        CPU_TYPE_X86 => "i386".to_string(),
+        CPU_TYPE_X86_64 => "x86_64".to_string(),
+        CPU_TYPE_ARM64 => "arm64".to_string(),
+        _ => format!("cpu type {:#x}", cpu_type),
+    }
+}
+
+/// versions are packed as a 16 bit major and 8 bit minor and patch, xxxx.yy.zz
+fn unpack_version(v: u32) -> (u32, u32, u32) {
+    (v >> 16, (v >> 8) & 0xff, v & 0xff)
+}
+
+fn read_u32(data: &[u8], pos: usize, big_endian: bool) -> Result<u32, Box<dyn Error>> {
+    let bytes: [u8; 4] = data
+        .get(pos..pos + 4)
+        .ok_or("unexpected end of Mach-O file")?
+        .try_into()?;
+    Ok(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn read_u64(data: &[u8], pos: usize, big_endian: bool) -> Result<u64, Box<dyn Error>> {
+    let bytes: [u8; 8] = data
+        .get(pos..pos + 8)
+        .ok_or("unexpected end of Mach-O file")?
+        .try_into()?;
+    Ok(if big_endian {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+/// convert a Darwin kernel version such as `20.0.0` into the matching macOS version
+/// returns None for an empty or unparsable kernel version
+pub fn kernel_to_macos(kernel: &str) -> Option<(u32, u32, u32)> {
+    let major = kernel.split('.').next()?.parse::<u32>().ok()?;
+    if major >= 20 {
+        Some((major - 9, 0, 0))
+    } else if major >= 4 {
+        Some((10, major - 4, 0))
+    } else {
+        None
+    }
+}
+
+/// check the slices of a kext executable against the `Arch` and `MaxKernel`
+/// settings of its Kernel > Add entry, return a list of warnings
+pub fn kext_warnings(slices: &[Slice], arch: &str, max_kernel: &str) -> Vec<String> {
+    let mut warnings = vec![];
+    let has = |a: &str| slices.iter().any(|s| s.arch == a);
+    match arch {
+        "x86_64" | "i386" => {
+            if !has(arch) {
+                warnings.push(format!(
+                    "Arch is set to {} but kext has no {} slice",
+                    arch, arch
+                ));
+            }
+        }
+        _ => {
+            if !has("x86_64") && !has("i386") {
+                warnings.push("kext has no Intel slice, it can not be loaded".to_string());
+            }
+        }
+    }
+
+    // 32 bit kernels were dropped with macOS 10.8, Darwin 12
+    if has("i386") && !has("x86_64") {
+        match kernel_to_macos(max_kernel) {
+            Some(v) if v < (10, 8, 0) => (),
+            _ => warnings.push(
+                "kext is i386 only, it can not load on macOS 10.8 or newer, set MaxKernel"
+                    .to_string(),
+            ),
+        }
+    }
+
+    if let Some(max_os) = kernel_to_macos(max_kernel) {
+        for slice in slices.iter().filter(|s| s.arch != "arm64") {
+            if let Some(min_os) = slice.min_os {
+                // compare major.minor only, MaxKernel covers all updates of a release,
+                // from macOS 11 on a release is a major version, 11.x is all Darwin 20
+                let too_new = if max_os.0 >= 11 {
+                    min_os.0 > max_os.0
+                } else {
+                    (min_os.0, min_os.1) > (max_os.0, max_os.1)
+                };
+                if too_new {
+                    warnings.push(format!(
+                        "{} slice needs macOS {} but MaxKernel {} allows at most macOS {}",
+                        slice.arch,
+                        slice.min_os_string(),
+                        max_kernel,
+                        if max_os.0 >= 11 {
+                            max_os.0.to_string()
+                        } else {
+                            format!("{}.{}", max_os.0, max_os.1)
+                        }
+                    ));
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn le(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    /// a 64 bit x86_64 executable with an LC_UUID and an LC_BUILD_VERSION for macOS 11.3
+    fn x86_64() -> Vec<u8> {
+        let mut data = le(&[MH_MAGIC_64, CPU_TYPE_X86_64, 3, 0xb, 2, 48, 0, 0]);
+        data.extend(le(&[LC_UUID, 24]));
+        data.extend(0..16u8);
+        data.extend(le(&[LC_BUILD_VERSION, 24, 1, 0x000b_0300, 0x000c_0000, 0]));
+        data
+    }
+
+    /// a 32 bit i386 executable with an LC_VERSION_MIN_MACOSX for 10.6.8
+    fn i386() -> Vec<u8> {
+        let mut data = le(&[MH_MAGIC, CPU_TYPE_X86, 3, 0xb, 1, 16, 0]);
+        data.extend(le(&[LC_VERSION_MIN_MACOSX, 16, 0x000a_0608, 0]));
+        data
+    }
+
+    /// both of the above in a fat binary, slices at 0x100 and 0x200
+    fn fat() -> Vec<u8> {
+        let (a, b) = (x86_64(), i386());
+        let mut data = vec![];
+        for w in [FAT_MAGIC, 2] {
+            data.extend(w.to_be_bytes());
+        }
+        for w in [CPU_TYPE_X86_64, 3, 0x100, a.len() as u32, 8] {
+            data.extend(w.to_be_bytes());
+        }
+        for w in [CPU_TYPE_X86, 3, 0x200, b.len() as u32, 8] {
+            data.extend(w.to_be_bytes());
+        }
+        data.resize(0x100, 0);
+        data.extend(&a);
+        data.resize(0x200, 0);
+        data.extend(&b);
+        data
+    }
+
+    #[test]
+    fn parses_thin() {
+        let slices = parse(&x86_64()).unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].arch, "x86_64");
+        assert_eq!(slices[0].min_os, Some((11, 3, 0)));
+        assert_eq!(
+            slices[0].uuid.as_deref(),
+            Some("00010203-0405-0607-0809-0A0B0C0D0E0F")
+        );
+        let slices = parse(&i386()).unwrap();
+        assert_eq!(slices[0].arch, "i386");
+        assert_eq!(slices[0].min_os_string(), "10.6.8");
+        assert_eq!(slices[0].uuid, None);
+    }
+
+    #[test]
+    fn parses_fat() {
+        let data = fat();
+        let slices = parse(&data).unwrap();
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].arch, "x86_64");
+        assert_eq!(slices[1].arch, "i386");
+        assert_eq!(slices[1].min_os, Some((10, 6, 8)));
+        assert_eq!(
+            slice_range(&data, "x86_64"),
+            Some(0x100..0x100 + x86_64().len())
+        );
+        assert_eq!(slice_range(&data, "i386"), Some(0x200..data.len()));
+        assert_eq!(slice_range(&data, "arm64"), None);
+        assert_eq!(slice_range(&i386(), "x86_64"), Some(0..i386().len()));
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(parse(b"not a mach-o file").is_err());
+        assert!(parse(&x86_64()[..40]).is_err());
+        let mut data = fat();
+        data[16..20].copy_from_slice(&0x1000u32.to_be_bytes());
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn warns_about_kexts() {
+        let slices = parse(&fat()).unwrap();
+        assert!(kext_warnings(&slices, "Any", "").is_empty());
+        assert_eq!(kext_warnings(&slices, "Any", "19.99.99").len(), 1);
+        assert!(kext_warnings(&slices, "Any", "20.99.99").is_empty());
+        let i386 = parse(&i386()).unwrap();
+        assert_eq!(kext_warnings(&i386, "x86_64", "").len(), 2);
+        assert!(kext_warnings(&i386, "i386", "11.99.99").is_empty());
+        assert_eq!(kernel_to_macos("21.0.0"), Some((12, 0, 0)));
+        assert_eq!(kernel_to_macos("15.0.0"), Some((10, 11, 0)));
+        assert_eq!(kernel_to_macos(""), None);
+    }
+}
@@ -1,10 +1,28 @@
+mod acpi;
+mod aml;
 mod build;
+mod bulk;
+mod clipboard;
+mod differences;
 mod draw;
 mod edit;
+mod filter;
+mod history;
+mod hw;
 mod init;
+mod lint;
+mod macho;
+mod matrix;
+mod migrate;
 mod parse_tex;
+mod patch;
+mod preset;
+mod replace;
 mod res;
+mod schema;
 mod snake;
+mod snapshot;
+mod ssdt;
 
 use fs_extra::dir::{copy, CopyOptions};
 use res::check_order;
@@ -24,7 +42,7 @@ use crossterm::{
 };
 
 use crate::edit::read_key;
-use crate::init::{guess_version, Manifest, Settings};
+use crate::init::{detect_versions, Manifest, Settings};
 use crate::res::Resources;
 
 const OCTOOL_VERSION: &str = include!(concat!(env!("OUT_DIR"), "/version.txt"));
@@ -42,6 +60,10 @@ fn process(
 
     init::init_oc_build(&mut resources, settings, stdout)?;
     init::init_plist(config_plist, &mut resources, settings, stdout)?;
+    if !settings.migrate_from.is_empty() && settings.oc_build_version != "not found" {
+        let version = settings.migrate_from.to_owned();
+        migrate::migrate_from_version(settings, resources, &version, stdout);
+    }
 
     let mut key = KeyCode::Char('q');
     let mut key_mod;
@@ -77,6 +99,15 @@ fn process(
                 stdout.flush().unwrap();
             }
             (key, key_mod) = read_key()?;
+            // keep the config.plist as it was in case this key changes it
+            let before = history::action(key, key_mod).map(|action| {
+                (
+                    action,
+                    resources.config_plist.clone(),
+                    settings.depth,
+                    settings.sec_num.clone(),
+                )
+            });
             match key {
                 KeyCode::Char('q') => {
                     if showing_info {
@@ -158,20 +189,43 @@ fn process(
                     found = vec![];
                     found_id = 0;
                     settings.find_string = String::new();
+                    // ctrl-f only searches the highlighted item and what is below it
+                    let section = if key_mod == KeyModifiers::CONTROL {
+                        settings.sec_num[..=settings.depth].to_vec()
+                    } else {
+                        vec![]
+                    };
                     write!(
                         stdout,
-                        "{}\r\x1B[2KEnter search term: {}\r\n\x1B[2K\x1B8",
+                        "{}\r\x1B[2KEnter search term or /regex/{}: {}\r\n\x1B[2K\x1B8",
                         cursor::Show,
+                        if section.is_empty() {
+                            String::new()
+                        } else {
+                            format!(
+                                " to find in {}",
+                                settings.sec_key[..=settings.depth].join("->")
+                            )
+                        },
                         cursor::SavePosition,
                     )
                     .unwrap();
                     edit::edit_string(&mut settings.find_string, None, stdout).unwrap();
                     write!(stdout, "{}", cursor::Hide).unwrap();
                     if settings.find_string.len() > 0 {
-                        edit::find(&settings.find_string, &resources.config_plist, &mut found);
+                        match edit::search_regex(&settings.find_string) {
+                            Ok(search) => {
+                                edit::find(&search, &resources.config_plist, &section, &mut found)
+                            }
+                            Err(e) => {
+                                write!(stdout, "\r\n\x1b[31mERROR:\x1b[0m {}\x1b[0K\r\n", e)?;
+                                stdout.flush()?;
+                                read_key()?;
+                            }
+                        }
                         if found.len() == 1 {
                             settings.depth = found[0].level;
-                            settings.sec_num = found[0].section;
+                            settings.sec_num = found[0].section.clone();
                             settings.find_string = String::new();
                             found_id = 0;
                         } else if found.len() > 1 {
@@ -216,13 +270,18 @@ fn process(
                             found_id = selection;
                             if selection > 0 {
                                 settings.depth = found[selection - 1].level;
-                                settings.sec_num = found[selection - 1].section;
+                                settings.sec_num = found[selection - 1].section.clone();
                             }
                         } else {
                             settings.find_string = String::new();
                         }
                     }
                 }
+                KeyCode::Char('F') => {
+                    replace::replace(settings, resources, stdout);
+                    stdout.flush()?;
+                    showing_info = true;
+                }
                 KeyCode::Char('n') => {
                     if found_id > 0 {
                         found_id += 1;
@@ -230,7 +289,7 @@ fn process(
                             found_id = 1;
                         }
                         settings.depth = found[found_id - 1].level;
-                        settings.sec_num = found[found_id - 1].section;
+                        settings.sec_num = found[found_id - 1].section.clone();
                     }
                 }
                 KeyCode::Char('O') => {
@@ -267,7 +326,9 @@ fn process(
                     showing_info = true;
                 }
                 KeyCode::Char('p') => {
-                    if edit::add_delete_value(settings, &mut resources.config_plist, true) {
+                    if clipboard::load(settings)
+                        && edit::add_delete_value(settings, &mut resources.config_plist, true)
+                    {
                         settings.add();
                     }
                 }
@@ -277,6 +338,7 @@ fn process(
                 }
                 KeyCode::Char('v') => {
                     if key_mod == KeyModifiers::CONTROL
+                        && clipboard::load(settings)
                         && edit::add_delete_value(settings, &mut resources.config_plist, true)
                     {
                         settings.add();
@@ -290,6 +352,25 @@ fn process(
                 KeyCode::End | KeyCode::Char('b') => {
                     settings.sec_num[settings.depth] = settings.sec_length[settings.depth] - 1
                 }
+                KeyCode::Char('[') | KeyCode::Char(']') => {
+                    let items = bulk::moving(settings);
+                    if let Some(order) = edit::move_items(
+                        settings,
+                        &mut resources.config_plist,
+                        &items,
+                        key == KeyCode::Char('['),
+                    ) {
+                        bulk::moved(settings, &order);
+                        settings.modified = true;
+                    }
+                }
+                KeyCode::Char('m') => bulk::toggle(settings),
+                KeyCode::Char('/') => filter::select(settings, resources, stdout),
+                KeyCode::Char('B') => {
+                    bulk::bulk(settings, resources, stdout);
+                    stdout.flush()?;
+                    showing_info = true;
+                }
                 KeyCode::Char(' ') => {
                     //                    if !showing_info {
                     edit::edit_value(
@@ -312,8 +393,11 @@ fn process(
                     for a in 0..=settings.depth {
                         search_str.push(settings.sec_key[a].to_owned());
                     }
-                    let valid_values =
+                    let mut valid_values =
                         parse_tex::parse_configuration(tex_path, search_str, 0, true, false);
+                    if valid_values.is_empty() {
+                        valid_values = aml::edit_values(settings, &resources.input_dir_path, false);
+                    }
                     edit::edit_value(
                         settings,
                         &mut resources.config_plist,
@@ -327,18 +411,26 @@ fn process(
                         false,
                     )?;
                 }
-                KeyCode::Char('K') => edit::edit_value(
-                    settings,
-                    &mut resources.config_plist,
-                    None,
-                    stdout,
-                    false,
-                    true,
-                )?,
+                KeyCode::Char('K') => {
+                    let valid_values = aml::edit_values(settings, &resources.input_dir_path, true);
+                    edit::edit_value(
+                        settings,
+                        &mut resources.config_plist,
+                        if valid_values.is_empty() {
+                            None
+                        } else {
+                            Some(&valid_values)
+                        },
+                        stdout,
+                        false,
+                        true,
+                    )?
+                }
                 KeyCode::Char('D') => {
                     if settings.sec_length[settings.depth] > 0 {
                         if edit::add_delete_value(settings, &mut resources.config_plist, false) {
                             settings.delete();
+                            clipboard::store(settings);
                         }
                     }
                 }
@@ -348,6 +440,7 @@ fn process(
                             if edit::add_delete_value(settings, &mut resources.config_plist, false)
                             {
                                 settings.delete();
+                                clipboard::store(settings);
                             }
                         }
                     }
@@ -369,18 +462,26 @@ fn process(
                             if edit::add_delete_value(settings, &mut resources.config_plist, false)
                             {
                                 settings.delete();
+                                clipboard::store(settings);
                             }
                         }
                     }
                 }
                 KeyCode::Char('y') => {
-                    edit::extract_value(settings, &mut resources.config_plist, false, true);
+                    let copied =
+                        edit::extract_value(settings, &mut resources.config_plist, false, true);
+                    if copied {
+                        clipboard::store(settings);
+                    }
                 }
                 KeyCode::Char('c') => {
-                    if key_mod == KeyModifiers::CONTROL {
-                        edit::extract_value(settings, &mut resources.config_plist, false, true);
+                    if key_mod == KeyModifiers::CONTROL
+                        && edit::extract_value(settings, &mut resources.config_plist, false, true)
+                    {
+                        clipboard::store(settings);
                     }
                 }
+                KeyCode::Char('"') => clipboard::select_register(settings, resources, stdout),
                 KeyCode::Char('V') => {
                     let mut parent_res = "OpenCorePkg".to_string();
                     if settings.is_resource() {
@@ -436,13 +537,38 @@ fn process(
                             &parent_res,
                             cursor::SavePosition,
                         )?;
+                            if &parent_res == "OpenCorePkg" {
+                                // mark the versions the keys in the config.plist fit
+                                let compatible = detect_versions(resources).compatible;
+                                for v in versions.iter_mut() {
+                                    let num = v.split("---").next().unwrap_or("").trim();
+                                    if compatible.iter().any(|c| c == num) {
+                                        v.push_str(" \x1b[32m(fits config.plist)\x1b[0m");
+                                    }
+                                }
+                            }
                             edit::edit_string(&mut new_ver, Some(&versions), stdout)?;
                             if &parent_res == "OpenCorePkg" {
+                                let old_sample = resources.sample_plist.clone();
+                                let old_ver = settings.oc_build_version.to_owned();
                                 settings.oc_build_version = new_ver;
                                 init::init_oc_build(&mut resources, settings, stdout)?;
                                 if settings.oc_build_version == "not found" {
                                     stdout.flush()?;
                                     showing_info = true;
+                                } else if settings.oc_build_version != old_ver {
+                                    // undo can't put the old version back, so don't let
+                                    // it restore a config.plist made for that version
+                                    settings.history.clear();
+                                    migrate::migrate(
+                                        settings,
+                                        resources,
+                                        &old_sample,
+                                        &old_ver,
+                                        stdout,
+                                    );
+                                    stdout.flush()?;
+                                    showing_info = true;
                                 }
                             } else {
                                 for (i, v) in versions.iter().enumerate() {
@@ -494,14 +620,33 @@ fn process(
                         read_key()?;
                     }
                 }
+                KeyCode::Char('u') => {
+                    settings.modified |= settings
+                        .history
+                        .undo(
+                            &mut resources.config_plist,
+                            &mut settings.depth,
+                            &mut settings.sec_num,
+                        )
+                        .is_some();
+                }
+                KeyCode::Char('r') if key_mod == KeyModifiers::CONTROL => {
+                    settings.modified |= settings
+                        .history
+                        .redo(
+                            &mut resources.config_plist,
+                            &mut settings.depth,
+                            &mut settings.sec_num,
+                        )
+                        .is_some();
+                }
                 KeyCode::Char('r') => {
-                    if settings.depth < 4 {
-                        let mut obj = String::new();
-                        for i in 0..settings.depth + 1 {
-                            obj.push_str(&settings.sec_key[i]);
-                            obj.push(' ');
-                        }
-                        write!(stdout,"\r\n{und}Press{res} '{grn}r{res}' again to reset {yel}{obj}{res}to \
+                    let mut obj = String::new();
+                    for i in 0..settings.depth + 1 {
+                        obj.push_str(&settings.sec_key[i]);
+                        obj.push(' ');
+                    }
+                    write!(stdout,"\r\n{und}Press{res} '{grn}r{res}' again to reset {yel}{obj}{res}to \
                                the Sample.plist values, any other key to cancel.{clr}\r\n{yel}You can use \
                                '{grn}p{yel}' to place old {grn}{cur}{yel} back into plist if needed{res}{clr}\r\n{clr}",
                             obj = &obj,
@@ -512,27 +657,21 @@ fn process(
                             res = "\x1b[0m",
                             clr = "\x1b[0K",
                         )?;
-                        stdout.flush()?;
-                        if read_key()?.0 == KeyCode::Char('r') {
-                            if edit::extract_value(settings, &resources.config_plist, false, true) {
-                                settings.modified = true;
-                                let tmp_item = settings.held_item.clone();
-                                let tmp_key = settings.held_key.clone();
-                                if edit::extract_value(
+                    stdout.flush()?;
+                    if read_key()?.0 == KeyCode::Char('r') {
+                        if edit::extract_value(settings, &resources.config_plist, false, true) {
+                            settings.modified = true;
+                            let tmp_item = settings.held_item.clone();
+                            let tmp_key = settings.held_key.clone();
+                            if edit::extract_value(settings, &resources.sample_plist, false, true) {
+                                let _ = edit::add_delete_value(
                                     settings,
-                                    &resources.sample_plist,
-                                    false,
+                                    &mut resources.config_plist,
                                     true,
-                                ) {
-                                    let _ = edit::add_delete_value(
-                                        settings,
-                                        &mut resources.config_plist,
-                                        true,
-                                    );
-                                }
-                                settings.held_key = tmp_key.to_owned();
-                                settings.held_item = tmp_item;
+                                );
                             }
+                            settings.held_key = tmp_key.to_owned();
+                            settings.held_item = tmp_item;
                         }
                     }
                 }
@@ -565,6 +704,38 @@ fn process(
                         showing_info = false;
                     }
                 }
+                KeyCode::Char('L') => {
+                    lint::show_report(settings, resources, stdout);
+                    stdout.flush()?;
+                    showing_info = true;
+                }
+                KeyCode::Char('N') => {
+                    snapshot::snapshot(settings, resources, stdout);
+                    showing_info = true;
+                }
+                KeyCode::Char('g') => {
+                    ssdt::generate(settings, resources, stdout);
+                    showing_info = true;
+                }
+                KeyCode::Char('A') => {
+                    preset::apply_preset(settings, resources, stdout);
+                    stdout.flush()?;
+                    showing_info = true;
+                }
+                KeyCode::Char('H') => {
+                    hw::show_profile(settings, resources, stdout);
+                    stdout.flush()?;
+                    showing_info = true;
+                }
+                KeyCode::Char('T')
+                    if settings.depth > 1
+                        && settings.sec_key[1] == "Patch"
+                        && ["ACPI", "Booter", "Kernel"].contains(&settings.sec_key[0].as_str()) =>
+                {
+                    patch::test_patch(settings, resources, stdout);
+                    stdout.flush()?;
+                    showing_info = true;
+                }
                 KeyCode::Char('S') => {
                     snake::snake(stdout)?;
                     read_key()?;
@@ -638,6 +809,22 @@ fn process(
                 }
                 _ => (),
             }
+            if key != KeyCode::Char('"') {
+                settings.clipboard.pending = None; // a register only applies to the next key
+            }
+            // the filter only has to be worked out again if the config.plist, or the
+            // Sample.plist and schema it is checked against, changed
+            let mut changed = matches!(key, KeyCode::Char('u') | KeyCode::Char('V'))
+                || key == KeyCode::Char('r') && key_mod == KeyModifiers::CONTROL;
+            if let Some((action, plist, depth, sec_num)) = before {
+                changed |=
+                    settings
+                        .history
+                        .record(action, plist, &resources.config_plist, depth, sec_num);
+            }
+            if changed {
+                filter::refresh(settings, resources);
+            }
             if key != KeyCode::Char('i')
                 && key != KeyCode::Char(' ')
                 && key != KeyCode::Char('s')
@@ -646,6 +833,14 @@ fn process(
                 && key != KeyCode::Char('P')
                 && key != KeyCode::Char('O')
                 && key != KeyCode::Char('I')
+                && key != KeyCode::Char('N')
+                && key != KeyCode::Char('L')
+                && key != KeyCode::Char('T')
+                && key != KeyCode::Char('g')
+                && key != KeyCode::Char('H')
+                && key != KeyCode::Char('A')
+                && key != KeyCode::Char('F')
+                && key != KeyCode::Char('B')
             {
                 showing_info = false;
             }
@@ -686,14 +881,17 @@ fn main() {
         dortania: Default::default(),
         octool_config: Default::default(),
         config_differences: Default::default(),
+        quirk_presets: Default::default(),
         resource_list: Default::default(),
         other: Default::default(),
         config_plist: plist::Value::Boolean(false),
         sample_plist: plist::Value::Boolean(false),
+        schema: Default::default(),
         input_dir_path: Default::default(),
-        working_dir_path: Default::default(),
+        working_dir_path: working_dir.to_owned(),
         open_core_binaries_path: Default::default(),
         open_core_source_path: Default::default(),
+        hw_root_path: PathBuf::from("/"),
     };
 
     //set default INPUT path, may be overriden by command line args
@@ -764,8 +962,17 @@ fn main() {
     resources.config_differences =
         res::get_serde_json_quiet("tool_config_files/config_differences.json").unwrap();
 
+    //load quirk presets, the A command reports if they are missing
+    resources.quirk_presets = res::get_serde_json_quiet(preset::PRESETS_FILE).unwrap_or_default();
+
     //    let mut config_file = working_dir.join("INPUT/config.plist");
     let mut config_file = resources.input_dir_path.join("config.plist");
+    let mut lint_mode = None; // Some(true) to also fix what is found
+    let mut acpi_source = None; // folder to dump ACPI tables from
+    let mut forced_version = false; // version set with -V
+    let mut regen_differences = false; // rebuild config_differences.json and exit
+    let mut matrix_versions = None; // OpenCore versions to validate the config.plist with
+    let mut matrix_download = false; // download the matrix versions that aren't in resources
     let args = env::args().skip(1).collect::<Vec<String>>();
     let mut args = args.iter();
     loop {
@@ -780,8 +987,16 @@ fn main() {
                             )
                             .unwrap();
                             write!(stdout, "OPTIONS\r\n\t-d   use debug versions instead of release\
+                                        \r\n\t-c   rebuild config_differences.json from every OpenCorePkg Sample.plist and exit\
+                                        \r\n\t-a   dump the ACPI tables of this Linux machine into INPUT/ACPI_dump\
+                                        \r\n\t-A dir  same as -a, but read the tables from dir instead\
                                         \r\n\t-h   print this help and exit\
+                                        \r\n\t-l   lint the config.plist for duplicates, Patch and rule problems and exit\
+                                        \r\n\t-L   same as -l, also disable duplicates and save a modified_ copy\
+                                        \r\n\t-m x.y.z,x.y.z  validate the config.plist with each OpenCore version, print a table and exit\
+                                        \r\n\t-M x.y.z,x.y.z  same as -m, but download the versions not in the resources folder\
                                         \r\n\t-v   show octool version info and exit\
+                                        \r\n\t-w dir  detect hardware for the H command from a copy of sys and proc in dir\
                                         \r\n\t-V x.y.z  manually force OpenCore version number to use\r\n").unwrap();
                             std::process::exit(0);
                         }
@@ -808,7 +1023,10 @@ fn main() {
                             std::process::exit(0);
                         }
                         'V' => match args.next() {
-                            Some(version) => setup.oc_build_version = version.to_owned(),
+                            Some(version) => {
+                                setup.oc_build_version = version.to_owned();
+                                forced_version = true;
+                            }
                             _ => {
                                 write!(stdout,
                                     "\r\n\x1B[33mERROR:\x1b[0m You need to supply a version number \
@@ -821,6 +1039,57 @@ fn main() {
                             }
                         },
                         'd' => setup.build_type = "debug".to_string(),
+                        'c' => regen_differences = true,
+                        'a' => acpi_source = Some(PathBuf::from(acpi::SYSFS_TABLES)),
+                        'A' => match args.next() {
+                            Some(dir) => acpi_source = Some(current_dir.join(dir)),
+                            _ => {
+                                write!(
+                                    stdout,
+                                    "\r\n\x1B[33mERROR:\x1b[0m You need to supply a folder \
+                                    with the -A option\r\n"
+                                )
+                                .unwrap();
+                                std::process::exit(0);
+                            }
+                        },
+                        'w' => match args.next() {
+                            Some(dir) => resources.hw_root_path = current_dir.join(dir),
+                            _ => {
+                                write!(
+                                    stdout,
+                                    "\r\n\x1B[33mERROR:\x1b[0m You need to supply a folder \
+                                    with the -w option\r\n"
+                                )
+                                .unwrap();
+                                std::process::exit(0);
+                            }
+                        },
+                        'm' | 'M' => match args.next() {
+                            Some(list) => {
+                                matrix_download = c == 'M';
+                                matrix_versions = Some(
+                                    list.split(',')
+                                        .map(|v| v.trim().to_string())
+                                        .filter(|v| !v.is_empty())
+                                        .collect::<Vec<String>>(),
+                                )
+                            }
+                            _ => {
+                                write!(
+                                    stdout,
+                                    "\r\n\x1B[33mERROR:\x1b[0m You need to supply a list of versions \
+                                    with the -{} option\r\n",
+                                    c
+                                )
+                                .unwrap();
+                                write!(stdout, "e.g. './octool -m \x1b[4m0.9.7,1.0.0\x1b[0m'\r\n")
+                                    .unwrap();
+                                std::process::exit(0);
+                            }
+                        },
+                        'l' => lint_mode = Some(false),
+                        'L' => lint_mode = Some(true),
                         _ => (),
                     }
                 }
@@ -882,6 +1151,44 @@ fn main() {
     }
 
     write!(stdout, "octool {}\r\n", setup.octool_version).unwrap();
+    if let Some(source) = acpi_source {
+        let dest = resources.input_dir_path.join(acpi::DUMP_DIR);
+        write!(
+            stdout,
+            "\r\n\x1b[32mDumping\x1b[0m ACPI tables from {:?} to {:?}\r\n",
+            source, dest
+        )
+        .unwrap();
+        let replace = !dest.exists() || {
+            write!(
+                stdout,
+                "\x1b[33m{:?} already exists\x1b[0m, press '\x1b[32my\x1b[0m' to replace it, \
+                 any other key to skip the dump\r\n",
+                dest
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+            read_key().unwrap().0 == KeyCode::Char('y')
+        };
+        if !replace {
+            write!(stdout, "Skipped the ACPI dump\r\n\n").unwrap();
+        } else {
+            match acpi::dump_tables(&source, &dest, &mut stdout) {
+                Ok(count) => {
+                    write!(stdout, "\x1b[32mDone\x1b[0m with {} tables\r\n\n", count).unwrap()
+                }
+                Err(e) => write!(
+                    stdout,
+                    "\x1b[31mERROR:\x1b[0m could not dump ACPI tables: {}\r\n\
+                     \treading the tables from {} usually needs root\r\n\n",
+                    e,
+                    acpi::SYSFS_TABLES
+                )
+                .unwrap(),
+            }
+        }
+    }
+
     match init::init_static(&mut resources, &mut setup, &mut stdout) {
         Ok(_) => (),
         Err(e) => {
@@ -893,6 +1200,30 @@ fn main() {
         }
     }
 
+    if regen_differences {
+        write!(
+            stdout,
+            "\r\n\x1b[32mRebuilding\x1b[0m {:?}\r\n",
+            resources
+                .working_dir_path
+                .join(differences::DIFFERENCES_FILE)
+        )
+        .unwrap();
+        let okay = match differences::regenerate(&mut resources, &mut stdout) {
+            Ok(count) => {
+                write!(stdout, "\x1b[32mDone\x1b[0m with {} added keys\r\n", count).unwrap();
+                true
+            }
+            Err(e) => {
+                write!(stdout, "\x1b[31mERROR:\x1b[0m {}\r\n", e).unwrap();
+                false
+            }
+        };
+        stdout.execute(cursor::Show).unwrap();
+        terminal::disable_raw_mode().unwrap();
+        exit(if okay { 0 } else { 1 });
+    }
+
     if !config_file.exists() {
         write!(
             stdout,
@@ -963,13 +1294,13 @@ fn main() {
             }
         }
 
+        let guess = detect_versions(&resources);
         if &setup.oc_build_version == "latest" {
-            let first_diff;
-            (setup.oc_build_version, first_diff) = guess_version(&resources);
-            //use the latest version of OpenCore as a guess if there have been no changes to the
-            //config.plist, this makes the assumption that the user wants to keep the OpenCore
+            setup.oc_build_version = guess.best.to_owned();
+            //use the latest version of OpenCore as a guess if the config.plist fits it as well
+            //as the best guess, this makes the assumption that the user wants to keep the OpenCore
             //version current, they can always use a Manifest or manually use an older version
-            if first_diff
+            if guess.latest
                 && resources.octool_config["use_latest_oc_on_guess"]
                     .as_bool()
                     .unwrap_or(true)
@@ -986,10 +1317,77 @@ fn main() {
             }
             write!(stdout, "\x1b[33mGUESSING:\x1b[0m at OpenCore version of \x1b[33m{}\x1b[0m based on the input config.plist file\r\n\
                 \tIf this is incorrect you can change the version used with the capital 'V' key on the next screen\r\n\
-                \tor run octool with the -V option and provide an OpenCore version number\r\n\n", setup.oc_build_version ).unwrap();
+                \tor run octool with the -V option and provide an OpenCore version number\r\n", setup.oc_build_version ).unwrap();
+            if let (Some(newest), Some(oldest)) =
+                (guess.compatible.first(), guess.compatible.last())
+            {
+                write!(
+                    stdout,
+                    "\tthe keys in the config.plist fit OpenCore \x1b[32m{}\x1b[0m to \x1b[32m{}\x1b[0m\r\n",
+                    oldest, newest
+                )
+                .unwrap();
+            }
+            if guess.conflicts > 0 {
+                write!(
+                    stdout,
+                    "\t\x1b[33m{}\x1b[0m keys in the config.plist don't fit any one OpenCore version\r\n",
+                    guess.conflicts
+                )
+                .unwrap();
+            }
+            writeln!(stdout).unwrap();
+        } else if forced_version
+            && !guess.best.is_empty()
+            && !guess.compatible.contains(&setup.oc_build_version)
+        {
+            // a config.plist made for another version than the one forced with -V
+            setup.migrate_from = guess.best;
         }
     }
     stdout.flush().unwrap();
+
+    if let Some(fix) = lint_mode {
+        let mut lint_okay = false;
+        if config_file.exists() {
+            setup.config_file_name = config_file.to_str().unwrap().to_owned();
+            lint_okay = lint::run_headless(&setup, &mut resources, fix, &mut stdout);
+        } else {
+            write!(
+                stdout,
+                "\x1b[31mERROR:\x1b[0m the -{} option needs a config.plist\r\n",
+                if fix { 'L' } else { 'l' }
+            )
+            .unwrap();
+        }
+        stdout.execute(cursor::Show).unwrap();
+        terminal::disable_raw_mode().unwrap();
+        exit(if lint_okay { 0 } else { 1 });
+    }
+
+    if let Some(versions) = matrix_versions {
+        let mut matrix_code = 1;
+        if config_file.exists() {
+            setup.config_file_name = config_file.to_str().unwrap().to_owned();
+            matrix_code = matrix::run_headless(
+                &setup,
+                &mut resources,
+                &versions,
+                matrix_download,
+                &mut stdout,
+            );
+        } else {
+            write!(
+                stdout,
+                "\x1b[31mERROR:\x1b[0m the -m option needs a config.plist\r\n"
+            )
+            .unwrap();
+        }
+        stdout.execute(cursor::Show).unwrap();
+        terminal::disable_raw_mode().unwrap();
+        exit(matrix_code);
+    }
+
     match process(
         &mut config_file,
         &current_dir,
@@ -0,0 +1,220 @@
+use crate::differences;
+use crate::init::{self, Settings};
+use crate::res::{self, Resources};
+use crate::schema;
+
+use plist::Value;
+
+use std::io::{Stdout, Write};
+use std::path::{Path, PathBuf};
+
+/// how the config.plist fared with one OpenCore version
+#[derive(PartialEq)]
+enum Result {
+    Pass,   // ocvalidate and the Sample.plist keys found no problem
+    KeysOk, // no ocvalidate to run, the Sample.plist keys found no problem
+    Fail,
+}
+
+/// outcome of checking the config.plist against one OpenCore version
+struct Row {
+    version: String,
+    result: Result,
+    error: String, // first problem found
+}
+
+/// validate the config.plist in `settings` against each of `versions` using that
+/// version's ocvalidate and Sample.plist from the resources folder, missing versions
+/// are only downloaded if `download` is set, then print a pass/fail table
+/// returns the exit code, 0 if every version passed ocvalidate, 1 if any failed and
+/// 2 if none failed but for some there was no ocvalidate and only the keys were checked
+pub fn run_headless(
+    settings: &Settings,
+    resources: &mut Resources,
+    versions: &[String],
+    download: bool,
+    stdout: &mut Stdout,
+) -> i32 {
+    let config_path = Path::new(&settings.config_file_name);
+    let mut config_keys = vec![];
+    differences::key_paths(&resources.config_plist, &mut vec![], &mut config_keys);
+
+    let mut rows = vec![];
+    for version in versions {
+        let mut ver_settings = Settings {
+            oc_build_version: version.to_owned(),
+            build_type: settings.build_type.to_owned(),
+            ..Default::default()
+        };
+        write!(stdout, "\r\n").unwrap();
+        let mut row = Row {
+            version: version.to_owned(),
+            result: Result::Fail,
+            error: String::new(),
+        };
+        if download {
+            if let Err(e) = init::init_oc_build(resources, &mut ver_settings, stdout) {
+                row.error = format!("could not get OpenCorePkg: {}", e);
+                rows.push(row);
+                continue;
+            }
+        } else if let Err(e) = load_cached(resources, &mut ver_settings) {
+            row.error = e;
+            rows.push(row);
+            continue;
+        }
+        if ver_settings.oc_build_version == "not found" {
+            row.error = "version not found in the dortania builds".to_string();
+            rows.push(row);
+            continue;
+        }
+        row.version = ver_settings.oc_build_version.to_owned(); // "latest" is resolved
+
+        // keys the config.plist has that this version's Sample.plist doesn't, parents
+        // the Sample.plist has no keys for, e.g. empty arrays, can't be checked
+        let mut sample_keys = vec![];
+        differences::key_paths(&resources.sample_plist, &mut vec![], &mut sample_keys);
+        let unknown = config_keys
+            .iter()
+            .filter(|k| {
+                !sample_keys.contains(k) && sample_keys.iter().any(|s| s[0] == k[0] && s[1] == k[1])
+            })
+            .collect::<Vec<&[String; 3]>>();
+        if let Some(k) = unknown.first() {
+            row.error = format!("{} not in Sample.plist", k.join("->"));
+            rows.push(row);
+            continue;
+        }
+
+        let ocvalidate_bin = init::ocvalidate_path(resources);
+        if !ocvalidate_bin.exists() {
+            row.result = Result::KeysOk;
+            row.error =
+                "ocvalidate not available, only the Sample.plist keys were checked".to_string();
+        } else {
+            match res::status(
+                ocvalidate_bin.to_str().unwrap(),
+                &[config_path.to_str().unwrap()],
+            ) {
+                Ok(out) => {
+                    if out.status.code() == Some(0) {
+                        row.result = Result::Pass;
+                    } else {
+                        let text = format!(
+                            "{}\n{}",
+                            String::from_utf8_lossy(&out.stdout),
+                            String::from_utf8_lossy(&out.stderr)
+                        );
+                        // ocvalidate's own problem lines come first
+                        row.error = first_error(&text)
+                            .unwrap_or(format!("ocvalidate exit code {:?}", out.status.code()));
+                    }
+                }
+                Err(e) => row.error = format!("could not run ocvalidate: {}", e),
+            }
+        }
+        rows.push(row);
+    }
+
+    write!(
+        stdout,
+        "\r\n\x1b[4mOpenCore validation of\x1b[0m {}\x1b[0K\r\n\r\n{:<10} {:<7} First error\x1b[0K\r\n",
+        settings.config_file_name, "Version", "Result"
+    )
+    .unwrap();
+    for row in &rows {
+        write!(
+            stdout,
+            "{:<10} {} {}\x1b[0K\r\n",
+            row.version,
+            match row.result {
+                Result::Pass => "\x1b[32mpass   \x1b[0m",
+                Result::KeysOk => "\x1b[33mkeys ok\x1b[0m",
+                Result::Fail => "\x1b[31mFAIL   \x1b[0m",
+            },
+            row.error,
+        )
+        .unwrap();
+    }
+    stdout.flush().unwrap();
+    if rows.iter().any(|r| r.result == Result::Fail) {
+        1
+    } else if rows.iter().any(|r| r.result == Result::KeysOk) {
+        write!(
+            stdout,
+            "\r\n\x1b[33mNOTICE:\x1b[0m ocvalidate didn't run for every version, exit code 2\x1b[0K\r\n"
+        )
+        .unwrap();
+        2
+    } else {
+        0
+    }
+}
+
+/// point `resources` at the OpenCorePkg source and binaries of `settings`.oc_build_version
+/// already in the resources folder without downloading anything, the binaries may
+/// be missing, in which case there is no ocvalidate to run
+fn load_cached(
+    resources: &mut Resources,
+    settings: &mut Settings,
+) -> std::result::Result<(), String> {
+    let versions = &resources.dortania["OpenCorePkg"]["versions"];
+    let mut index = 0;
+    loop {
+        match versions[index]["version"].as_str() {
+            Some(v) if v == settings.oc_build_version || settings.oc_build_version == "latest" => {
+                settings.oc_build_version = v.to_owned();
+                break;
+            }
+            Some(_) => index += 1,
+            None => return Err("version not found in the dortania builds".to_string()),
+        }
+    }
+    // the newest version lives in the opencorepkg_path folder, see init::init_oc_build
+    let source = if index == 0 {
+        PathBuf::from(
+            resources.octool_config["opencorepkg_path"]
+                .as_str()
+                .unwrap_or(""),
+        )
+    } else {
+        PathBuf::from(format!(
+            "resources/OpenCorePkg-{}",
+            settings.oc_build_version
+        ))
+    };
+    let sample = source.join("Docs/Sample.plist");
+    if !sample.exists() {
+        return Err(format!(
+            "OpenCorePkg {} is not in the resources folder, use -M to download it",
+            settings.oc_build_version
+        ));
+    }
+    resources.sample_plist =
+        Value::from_file(&sample).map_err(|e| format!("{:?}: {}", sample, e))?;
+    resources.schema = schema::load(&source.join("Docs"));
+    resources.open_core_source_path = source;
+
+    let url = versions[index]["links"][&settings.build_type]
+        .as_str()
+        .unwrap_or("");
+    let dir = Path::new(url)
+        .file_stem()
+        .map(|d| d.to_string_lossy().to_string())
+        .unwrap_or_default();
+    resources.open_core_binaries_path = Path::new("resources").join(dir);
+    Ok(())
+}
+
+/// first line of ocvalidate output that reports a problem
+fn first_error(text: &str) -> Option<String> {
+    text.lines()
+        .map(|l| l.trim())
+        .find(|l| {
+            !l.is_empty()
+                && !l.starts_with("NOTE:")
+                && !l.starts_with("Completed validating")
+                && !l.starts_with("Serialisation returns")
+        })
+        .map(|l| l.to_string())
+}
@@ -0,0 +1,611 @@
+use crate::edit::read_key;
+use crate::init::Settings;
+use crate::res::{self, Resources};
+use crate::snapshot;
+
+use crossterm::event::KeyCode;
+use plist::Value;
+
+use std::io::{Stdout, Write};
+use std::path::Path;
+
+/// path segment standing for every item of an array
+const ALL_ITEMS: &str = "[]";
+
+/// sections whose keys are chosen by the user, e.g. device paths and NVRAM GUIDs,
+/// the Sample.plist entries in them are examples, not a format to migrate
+pub const FREE_FORM: &[[&str; 2]] = &[
+    ["DeviceProperties", "Add"],
+    ["DeviceProperties", "Delete"],
+    ["NVRAM", "Add"],
+    ["NVRAM", "Delete"],
+    ["NVRAM", "LegacySchema"],
+];
+
+/// a difference between two Sample.plist files that may need the config.plist changed
+#[derive(Debug)]
+enum Change {
+    Rename { from: String, to: String },
+    Retype { key: String, template: Value },
+    RetypeItems { template: Value },
+    Remove { key: String },
+    Add { key: String, default: Value },
+}
+
+/// a change and the dictionary or array it applies to
+#[derive(Debug)]
+struct Migration {
+    path: Vec<String>, // keys down to the parent, ALL_ITEMS for each item of an array
+    change: Change,
+}
+
+impl Migration {
+    fn describe(&self) -> String {
+        let path = self.path.join("->");
+        match &self.change {
+            Change::Rename { from, to } => format!(
+                "{}->{} looks renamed to {}, its value moves to {}",
+                path, from, to, to
+            ),
+            Change::Retype { key, template } => {
+                format!("{}->{} is now {}", path, key, kind(template))
+            }
+            Change::RetypeItems { template } => {
+                format!("{} items are now {}", path, kind(template))
+            }
+            Change::Remove { key } => format!("{}->{} was removed", path, key),
+            Change::Add { key, default } => {
+                format!("{}->{} was added, default {}", path, key, show(default))
+            }
+        }
+    }
+}
+
+/// compare the Sample.plist of the version the config.plist was made for with the
+/// one now loaded, list the changes the config.plist needs and apply the accepted ones
+pub fn migrate(
+    settings: &mut Settings,
+    resources: &mut Resources,
+    old_sample: &Value,
+    old_version: &str,
+    stdout: &mut Stdout,
+) {
+    let mut migrations = vec![];
+    diff(
+        old_sample,
+        &resources.sample_plist,
+        &mut vec![],
+        &mut migrations,
+    );
+    // only report what changes something in this config.plist
+    migrations.retain(|m| apply(m, &mut resources.config_plist.clone()) > 0);
+    write!(
+        stdout,
+        "\r\n\x1b[2K\x1b[4mMigrating\x1b[0m config.plist from OpenCore {} to {}\x1b[0K\r\n",
+        old_version, settings.oc_build_version
+    )
+    .unwrap();
+    if migrations.is_empty() {
+        write!(stdout, "\x1b[32mNo changes needed\x1b[0m\x1b[0K\r\n").unwrap();
+        return;
+    }
+    write!(
+        stdout,
+        "'\x1b[32my\x1b[0m' accept  '\x1b[32mn\x1b[0m' reject  \
+         '\x1b[32mA\x1b[0m' accept this and the rest  '\x1b[32mESC\x1b[0m' stop without changes\x1b[0K\r\n"
+    )
+    .unwrap();
+    let mut accepted = vec![];
+    let mut accept_rest = false;
+    for migration in &migrations {
+        let count = apply(migration, &mut resources.config_plist.clone());
+        write!(
+            stdout,
+            "  {} \x1b[33m({} in config.plist)\x1b[0m\x1b[0K",
+            migration.describe(),
+            count
+        )
+        .unwrap();
+        // a rename is a guess, so it is always confirmed
+        if !accept_rest || matches!(migration.change, Change::Rename { .. }) {
+            stdout.flush().unwrap();
+            match read_key().unwrap().0 {
+                KeyCode::Char('y') => (),
+                KeyCode::Char('A') => accept_rest = true,
+                KeyCode::Esc => {
+                    write!(stdout, "\r\n\x1b[33mMigration cancelled\x1b[0m\x1b[0K\r\n").unwrap();
+                    return;
+                }
+                _ => {
+                    write!(stdout, " \x1b[31mrejected\x1b[0m\r\n").unwrap();
+                    continue;
+                }
+            }
+        }
+        write!(stdout, " \x1b[32maccepted\x1b[0m\r\n").unwrap();
+        accepted.push(migration);
+    }
+    let mut count = 0;
+    for migration in accepted {
+        count += apply(migration, &mut resources.config_plist);
+    }
+    if count > 0 {
+        settings.modified = true;
+    }
+    write!(
+        stdout,
+        "\x1b[32mChanged\x1b[0m {} values in the config.plist\x1b[0K\r\n",
+        count
+    )
+    .unwrap();
+}
+
+/// get the Sample.plist of `version` and migrate the config.plist from it
+pub fn migrate_from_version(
+    settings: &mut Settings,
+    resources: &mut Resources,
+    version: &str,
+    stdout: &mut Stdout,
+) {
+    match sample_for_version(version) {
+        Ok(old_sample) => migrate(settings, resources, &old_sample, version, stdout),
+        Err(e) => write!(
+            stdout,
+            "\x1b[31mERROR:\x1b[0m getting the OpenCore {} Sample.plist for migration: {}\r\n",
+            version, e
+        )
+        .unwrap(),
+    }
+}
+
+/// the Sample.plist of OpenCore `version`, from the local OpenCorePkg source if there
+/// is one, otherwise downloaded once and kept in the resources folder
+pub fn sample_for_version(version: &str) -> Result<Value, String> {
+    let local = Path::new("resources")
+        .join(format!("OpenCorePkg-{}", version))
+        .join("Docs/Sample.plist");
+    let sample_path = if local.exists() {
+        local
+    } else {
+        let path = Path::new("resources").join(format!("Sample-{}.plist", version));
+        if !path.exists() {
+            let url = format!(
+                "https://raw.githubusercontent.com/acidanthera/OpenCorePkg/{}/Docs/Sample.plist",
+                version
+            );
+            std::fs::create_dir_all("resources").map_err(|e| e.to_string())?;
+            res::curl_file(&url, &path).map_err(|e| e.to_string())?;
+        }
+        path
+    };
+    Value::from_file(&sample_path).map_err(|e| {
+        // don't keep a failed download, e.g. a 404 page for an unknown version
+        if !sample_path.ends_with("Docs/Sample.plist") {
+            let _ = std::fs::remove_file(&sample_path);
+        }
+        format!("{:?}: {}", sample_path, e)
+    })
+}
+
+/// walk the `old` and `new` Sample.plist values side by side and gather the changes
+fn diff(old: &Value, new: &Value, path: &mut Vec<String>, found: &mut Vec<Migration>) {
+    if FREE_FORM.iter().any(|f| path == f) {
+        return;
+    }
+    match (old, new) {
+        (Value::Dictionary(old), Value::Dictionary(new)) => {
+            let keys = |d: &plist::Dictionary, other: &plist::Dictionary| -> Vec<String> {
+                d.keys()
+                    .filter(|k| !k.starts_with('#') && !other.contains_key(k))
+                    .cloned()
+                    .collect()
+            };
+            let mut removed = keys(old, new);
+            let mut added = keys(new, old);
+
+            // the only removed and the only added key, of the same type and with related
+            // names, may be a rename, with more of either there is no telling which is which
+            if removed.len() == 1
+                && added.len() == 1
+                && old.get(&removed[0]).map(kind) == new.get(&added[0]).map(kind)
+                && related(&removed[0], &added[0])
+            {
+                found.push(Migration {
+                    path: path.clone(),
+                    change: Change::Rename {
+                        from: removed.remove(0),
+                        to: added.remove(0),
+                    },
+                });
+            }
+            for key in removed {
+                found.push(Migration {
+                    path: path.clone(),
+                    change: Change::Remove { key },
+                });
+            }
+            for key in added {
+                found.push(Migration {
+                    path: path.clone(),
+                    change: Change::Add {
+                        default: new.get(&key).unwrap().clone(),
+                        key,
+                    },
+                });
+            }
+
+            for (key, old_val) in old.iter().filter(|(k, _)| !k.starts_with('#')) {
+                let new_val = match new.get(key) {
+                    Some(v) => v,
+                    None => continue,
+                };
+                if kind(old_val) == kind(new_val) {
+                    path.push(key.to_owned());
+                    diff(old_val, new_val, path, found);
+                    path.pop();
+                } else {
+                    found.push(Migration {
+                        path: path.clone(),
+                        change: Change::Retype {
+                            key: key.to_owned(),
+                            template: new_val.clone(),
+                        },
+                    });
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            if let (Some(old_item), Some(new_item)) = (old.first(), new.first()) {
+                if kind(old_item) == kind(new_item) {
+                    // items are examples, keys added to them get cleared values
+                    let mut new_item = new_item.clone();
+                    snapshot::clear_values(&mut new_item);
+                    path.push(ALL_ITEMS.to_string());
+                    diff(old_item, &new_item, path, found);
+                    path.pop();
+                } else {
+                    let mut template = new_item.clone();
+                    snapshot::clear_values(&mut template);
+                    found.push(Migration {
+                        path: path.clone(),
+                        change: Change::RetypeItems { template },
+                    });
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// apply a migration to every place it fits in `config`, returns the number of values changed
+fn apply(migration: &Migration, config: &mut Value) -> usize {
+    let mut count = 0;
+    for_each_at(
+        config,
+        &migration.path,
+        &mut |val| match (&migration.change, val) {
+            (Change::Rename { from, to }, Value::Dictionary(d)) if !d.contains_key(to) => {
+                if let Some(v) = d.remove(from) {
+                    d.insert(to.to_owned(), v);
+                    count += 1;
+                }
+            }
+            (Change::Retype { key, template }, Value::Dictionary(d)) => {
+                if let Some(v) = d.get_mut(key) {
+                    if kind(v) != kind(template) {
+                        *v = convert(v, template);
+                        count += 1;
+                    }
+                }
+            }
+            (Change::RetypeItems { template }, Value::Array(a)) => {
+                for v in a.iter_mut().filter(|v| kind(v) != kind(template)) {
+                    *v = convert(v, template);
+                    count += 1;
+                }
+            }
+            (Change::Remove { key }, Value::Dictionary(d)) => {
+                count += d.remove(key).is_some() as usize;
+            }
+            (Change::Add { key, default }, Value::Dictionary(d)) if !d.contains_key(key) => {
+                d.insert(key.to_owned(), default.clone());
+                count += 1;
+            }
+            _ => (),
+        },
+    );
+    count
+}
+
+/// call `f` on every value in `val` found at `path`
+fn for_each_at(val: &mut Value, path: &[String], f: &mut dyn FnMut(&mut Value)) {
+    match path.split_first() {
+        None => f(val),
+        Some((key, rest)) if key == ALL_ITEMS => {
+            if let Value::Array(a) = val {
+                for item in a {
+                    for_each_at(item, rest, f);
+                }
+            }
+        }
+        Some((key, rest)) => {
+            if let Some(v) = val.as_dictionary_mut().and_then(|d| d.get_mut(key)) {
+                for_each_at(v, rest, f);
+            }
+        }
+    }
+}
+
+/// carry `val` over to the type of `template`, falls back to the template value
+/// strings becoming dictionaries are taken as an entry Path with # meaning disabled,
+/// as with the UEFI > Drivers entries before OpenCore 0.7.3
+fn convert(val: &Value, template: &Value) -> Value {
+    match (val, template) {
+        (Value::String(s), Value::Dictionary(t)) => {
+            let mut d = t.clone();
+            let path_key = ["Path", "BundlePath", "Name"]
+                .into_iter()
+                .find(|k| d.contains_key(k))
+                .unwrap_or("Path");
+            d.insert(
+                path_key.to_string(),
+                Value::String(s.trim_start_matches('#').to_string()),
+            );
+            if d.contains_key("Enabled") {
+                d.insert("Enabled".to_string(), Value::Boolean(!s.starts_with('#')));
+            }
+            Value::Dictionary(d)
+        }
+        (Value::Dictionary(d), Value::String(_)) => {
+            let path = d
+                .get("Path")
+                .and_then(|p| p.as_string())
+                .unwrap_or("")
+                .to_string();
+            match d.get("Enabled") {
+                Some(Value::Boolean(false)) => Value::String(format!("#{}", path)),
+                _ => Value::String(path),
+            }
+        }
+        (Value::Boolean(b), Value::Integer(_)) => Value::Integer((*b as i64).into()),
+        (Value::Integer(i), Value::Boolean(_)) => Value::Boolean(i.as_signed().unwrap_or(0) != 0),
+        (Value::Integer(i), Value::String(_)) => Value::String(i.to_string()),
+        (Value::String(s), Value::Integer(_)) => match s.parse::<i64>() {
+            Ok(i) => Value::Integer(i.into()),
+            Err(_) => template.clone(),
+        },
+        (Value::Data(d), Value::Integer(_)) if d.len() <= 8 => {
+            let mut bytes = [0u8; 8];
+            bytes[..d.len()].copy_from_slice(d);
+            Value::Integer(i64::from_le_bytes(bytes).into())
+        }
+        (Value::Integer(i), Value::Data(t)) => {
+            let bytes = i.as_signed().unwrap_or(0).to_le_bytes();
+            Value::Data(bytes[..t.len().clamp(1, 8)].to_vec())
+        }
+        _ => template.clone(),
+    }
+}
+
+/// names that look like one was renamed to the other, e.g. AudioOut and
+/// AudioOutMask or AdviseWindows and AdviseFeatures
+fn related(a: &str, b: &str) -> bool {
+    let prefix = a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count();
+    let suffix = a
+        .chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    a.contains(b) || b.contains(a) || prefix >= 5 || suffix >= 5
+}
+
+fn kind(val: &Value) -> &'static str {
+    match val {
+        Value::Array(_) => "array",
+        Value::Dictionary(_) => "dictionary",
+        Value::Boolean(_) => "boolean",
+        Value::Data(_) => "data",
+        Value::Integer(_) => "integer",
+        Value::String(_) => "string",
+        _ => "other",
+    }
+}
+
+fn show(val: &Value) -> String {
+    match val {
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        Value::Data(d) => format!("<{}>", hex::encode_upper(d)),
+        v => kind(v).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dict(pairs: Vec<(&str, Value)>) -> Value {
+        Value::Dictionary(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    fn int(i: i64) -> Value {
+        Value::Integer(i.into())
+    }
+
+    fn changes(old: &Value, new: &Value) -> Vec<Migration> {
+        let mut found = vec![];
+        diff(old, new, &mut vec![], &mut found);
+        found
+    }
+
+    #[test]
+    fn finds_a_single_rename() {
+        let old = dict(vec![(
+            "Misc",
+            dict(vec![("AdviseWindows", Value::Boolean(false))]),
+        )]);
+        let new = dict(vec![(
+            "Misc",
+            dict(vec![("AdviseFeatures", Value::Boolean(false))]),
+        )]);
+        let found = changes(&old, &new);
+        assert_eq!(found.len(), 1);
+        assert!(matches!(&found[0].change, Change::Rename { from, to }
+            if from == "AdviseWindows" && to == "AdviseFeatures"));
+        assert_eq!(
+            found[0].describe(),
+            "Misc->AdviseWindows looks renamed to AdviseFeatures, its value moves to AdviseFeatures"
+        );
+
+        let mut config = dict(vec![(
+            "Misc",
+            dict(vec![("AdviseWindows", Value::Boolean(true))]),
+        )]);
+        assert_eq!(apply(&found[0], &mut config), 1);
+        assert_eq!(
+            config,
+            dict(vec![(
+                "Misc",
+                dict(vec![("AdviseFeatures", Value::Boolean(true))])
+            )])
+        );
+    }
+
+    #[test]
+    fn several_changes_are_not_renames() {
+        let old = dict(vec![("AudioOut", int(0)), ("AudioCodec", int(0))]);
+        let new = dict(vec![("AudioOutMask", int(0)), ("AudioCodecs", int(0))]);
+        let found = changes(&old, &new);
+        assert_eq!(found.len(), 4);
+        assert!(!found
+            .iter()
+            .any(|m| matches!(m.change, Change::Rename { .. })));
+
+        // a different type or an unrelated name is not a rename either
+        let found = changes(
+            &dict(vec![("Timeout", int(5))]),
+            &dict(vec![("Timeout2", Value::Boolean(true))]),
+        );
+        assert!(!found
+            .iter()
+            .any(|m| matches!(m.change, Change::Rename { .. })));
+        let found = changes(
+            &dict(vec![("Timeout", int(5))]),
+            &dict(vec![("Picker", int(5))]),
+        );
+        assert!(!found
+            .iter()
+            .any(|m| matches!(m.change, Change::Rename { .. })));
+    }
+
+    #[test]
+    fn adds_removes_and_skips_free_form() {
+        let old = dict(vec![
+            ("Booter", dict(vec![("Old", int(1)), ("#Comment", int(1))])),
+            ("NVRAM", dict(vec![("Add", dict(vec![("GUID-A", int(1))]))])),
+        ]);
+        let new = dict(vec![
+            (
+                "Booter",
+                dict(vec![("Quirk", Value::Boolean(false)), ("Other", int(2))]),
+            ),
+            ("NVRAM", dict(vec![("Add", dict(vec![("GUID-B", int(1))]))])),
+        ]);
+        let found = changes(&old, &new);
+        let described: Vec<String> = found.iter().map(|m| m.describe()).collect();
+        assert_eq!(
+            described,
+            vec![
+                "Booter->Old was removed",
+                "Booter->Quirk was added, default false",
+                "Booter->Other was added, default 2",
+            ]
+        );
+        let mut config = dict(vec![(
+            "Booter",
+            dict(vec![("Old", int(1)), ("Quirk", Value::Boolean(true))]),
+        )]);
+        let changed: usize = found.iter().map(|m| apply(m, &mut config)).sum();
+        assert_eq!(changed, 2);
+        assert_eq!(
+            config,
+            dict(vec![(
+                "Booter",
+                dict(vec![("Quirk", Value::Boolean(true)), ("Other", int(2))])
+            )])
+        );
+    }
+
+    #[test]
+    fn retypes_array_items() {
+        // UEFI > Drivers went from strings to dictionaries in OpenCore 0.7.3
+        let old = dict(vec![(
+            "Drivers",
+            Value::Array(vec![Value::String("OpenRuntime.efi".into())]),
+        )]);
+        let entry = dict(vec![
+            ("Path", Value::String("OpenCanopy.efi".into())),
+            ("Enabled", Value::Boolean(true)),
+            ("Arguments", Value::String("".into())),
+        ]);
+        let new = dict(vec![("Drivers", Value::Array(vec![entry]))]);
+        let found = changes(&old, &new);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].describe(), "Drivers items are now dictionary");
+
+        let mut config = dict(vec![(
+            "Drivers",
+            Value::Array(vec![
+                Value::String("A.efi".into()),
+                Value::String("#B.efi".into()),
+            ]),
+        )]);
+        assert_eq!(apply(&found[0], &mut config), 2);
+        let drivers = config.as_dictionary().unwrap()["Drivers"]
+            .as_array()
+            .unwrap();
+        assert_eq!(
+            drivers[0].as_dictionary().unwrap()["Path"],
+            Value::String("A.efi".into())
+        );
+        assert_eq!(
+            drivers[1].as_dictionary().unwrap()["Enabled"],
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            drivers[1].as_dictionary().unwrap()["Arguments"],
+            Value::String("".into())
+        );
+    }
+
+    #[test]
+    fn converts_values() {
+        let t = |v: Value, template: Value| convert(&v, &template);
+        assert_eq!(t(Value::Boolean(true), int(0)), int(1));
+        assert_eq!(t(int(2), Value::Boolean(false)), Value::Boolean(true));
+        assert_eq!(t(Value::String("12".into()), int(0)), int(12));
+        assert_eq!(t(Value::String("x".into()), int(7)), int(7));
+        assert_eq!(t(Value::Data(vec![1, 2]), int(0)), int(0x0201));
+        assert_eq!(
+            t(int(0x0201), Value::Data(vec![0; 4])),
+            Value::Data(vec![1, 2, 0, 0])
+        );
+        let disabled = dict(vec![
+            ("Path", Value::String("A.efi".into())),
+            ("Enabled", Value::Boolean(false)),
+        ]);
+        assert_eq!(
+            t(disabled, Value::String("".into())),
+            Value::String("#A.efi".into())
+        );
+    }
+
+    #[test]
+    fn related_names() {
+        assert!(related("AudioOut", "AudioOutMask"));
+        assert!(related("AdviseWindows", "AdviseFeatures"));
+        assert!(!related("Timeout", "Picker"));
+    }
+}
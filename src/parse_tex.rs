@@ -193,7 +193,7 @@ pub fn parse_configuration(
 ///
 /// TODO: pass back attributes so formatting/mode can exist for more than 1 line
 ///
-fn parse_line(
+pub fn parse_line(
     line: &str,
     columns: i32,
     width: i32,
@@ -0,0 +1,388 @@
+use crate::edit::{self, read_key};
+use crate::init::Settings;
+use crate::macho;
+use crate::res::Resources;
+
+use crossterm::cursor;
+use crossterm::event::KeyCode;
+use plist::{Dictionary, Value};
+
+use std::fs;
+use std::io::{Stdout, Write};
+use std::path::PathBuf;
+
+/// maximum number of match offsets listed in the test report
+const MAX_LISTED: usize = 16;
+
+/// the fields of an ACPI, Booter or Kernel > Patch entry used when patching
+#[derive(Debug, Default)]
+pub struct Patch {
+    pub find: Vec<u8>,
+    pub mask: Vec<u8>,
+    pub replace: Vec<u8>,
+    pub replace_mask: Vec<u8>,
+    pub skip: usize,  // number of matches to skip before patching
+    pub count: usize, // number of matches to patch, 0 for all
+    pub limit: usize, // number of bytes to search, 0 for all
+    pub base: String, // symbol or ACPI path to start from
+    pub arch: String, // Kernel and Booter patches only
+}
+
+impl Patch {
+    /// read a patch from its plist dictionary, missing fields use their defaults
+    pub fn from_entry(d: &Dictionary) -> Patch {
+        let data = |k: &str| match d.get(k) {
+            Some(Value::Data(v)) => v.to_owned(),
+            _ => vec![],
+        };
+        let int = |k: &str| match d.get(k) {
+            Some(Value::Integer(i)) => i.as_unsigned().unwrap_or(0) as usize,
+            _ => 0,
+        };
+        let string = |k: &str| {
+            d.get(k)
+                .and_then(|s| s.as_string())
+                .unwrap_or("")
+                .to_owned()
+        };
+        Patch {
+            find: data("Find"),
+            mask: data("Mask"),
+            replace: data("Replace"),
+            replace_mask: data("ReplaceMask"),
+            skip: int("Skip"),
+            count: int("Count"),
+            limit: int("Limit"),
+            base: string("Base"),
+            arch: string("Arch"),
+        }
+    }
+
+    /// check that the patch can be applied at all
+    pub fn check(&self) -> Result<(), String> {
+        if self.find.is_empty() {
+            return Err("Find is empty, patching at Base alone can not be tested".to_string());
+        }
+        if self.find.len() != self.replace.len() {
+            return Err("Find and Replace are not the same length".to_string());
+        }
+        if !self.mask.is_empty() && self.mask.len() != self.find.len() {
+            return Err("Mask is not the same length as Find".to_string());
+        }
+        if !self.replace_mask.is_empty() && self.replace_mask.len() != self.replace.len() {
+            return Err("ReplaceMask is not the same length as Replace".to_string());
+        }
+        Ok(())
+    }
+
+    fn matches_at(&self, data: &[u8], pos: usize) -> bool {
+        self.find.iter().enumerate().all(|(i, f)| {
+            let m = self.mask.get(i).copied().unwrap_or(0xff);
+            data[pos + i] & m == f & m
+        })
+    }
+}
+
+/// result of applying a patch
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub found: Vec<usize>, // offset of every match OpenCore sees, matches don't overlap
+    pub patched: Vec<usize>, // offsets that were actually patched
+}
+
+/// apply `patch` to `data` the same way OpenCore does, only `range` is searched
+/// matches are skipped `Skip` times, then up to `Count` matches are replaced
+/// searching resumes after the end of each match, skipped or replaced
+pub fn apply(patch: &Patch, data: &mut [u8], range: std::ops::Range<usize>) -> Outcome {
+    let mut outcome = Outcome::default();
+    let len = patch.find.len();
+    let mut end = range.end.min(data.len());
+    if patch.limit > 0 {
+        end = end.min(range.start + patch.limit);
+    }
+    if len == 0 || end < range.start + len {
+        return outcome;
+    }
+    let last = end - len;
+
+    let mut pos = range.start;
+    while pos <= last {
+        if patch.matches_at(data, pos) {
+            outcome.found.push(pos);
+            pos += len;
+        } else {
+            pos += 1;
+        }
+    }
+
+    let mut skip = patch.skip;
+    let mut pos = range.start;
+    while pos <= last {
+        if !patch.matches_at(data, pos) {
+            pos += 1;
+            continue;
+        }
+        if skip > 0 {
+            skip -= 1;
+            pos += len;
+            continue;
+        }
+        for i in 0..len {
+            let m = patch.replace_mask.get(i).copied().unwrap_or(0xff);
+            data[pos + i] = (data[pos + i] & !m) | (patch.replace[i] & m);
+        }
+        outcome.patched.push(pos);
+        if patch.count > 0 && outcome.patched.len() == patch.count {
+            break;
+        }
+        pos += len;
+    }
+    outcome
+}
+
+/// test the highlighted ACPI, Booter or Kernel > Patch entry against a user
+/// selected file, report the matches and optionally write the patched copy
+pub fn test_patch(settings: &Settings, resources: &Resources, stdout: &mut Stdout) {
+    let entry = match resources
+        .config_plist
+        .as_dictionary()
+        .and_then(|d| d.get(&settings.sec_key[0]))
+        .and_then(|s| s.as_dictionary())
+        .and_then(|s| s.get("Patch"))
+        .and_then(|a| a.as_array())
+        .and_then(|a| a.get(settings.sec_num[2]))
+        .and_then(|e| e.as_dictionary())
+    {
+        Some(e) => e,
+        None => return,
+    };
+    let patch = Patch::from_entry(entry);
+
+    write!(
+        stdout,
+        "\x1b[2K\r\n\x1b[2KEnter 'path of file' to test the patch against or drop file here: {}\x1b7\r\n\x1b[2K\x1b8",
+        cursor::Show
+    )
+    .unwrap();
+    let mut file_name = String::new();
+    edit::edit_string(&mut file_name, None, stdout).unwrap();
+    write!(stdout, "{}\r\n\x1b[2K", cursor::Hide).unwrap();
+    file_name = file_name.replace("\\ ", " ");
+    let file_name = PathBuf::from(&file_name.trim());
+    let mut data = match fs::read(&file_name) {
+        Ok(d) => d,
+        Err(e) => {
+            write!(
+                stdout,
+                "\x1b[31mERROR: \x1b[0mcould not read {:?}: {}\x1b[0K\r\n\x1b[2K",
+                file_name, e
+            )
+            .unwrap();
+            return;
+        }
+    };
+
+    if let Err(e) = patch.check() {
+        write!(stdout, "\x1b[31mERROR: \x1b[0m{}\x1b[0K\r\n\x1b[2K", e).unwrap();
+        return;
+    }
+    if !patch.base.is_empty() {
+        write!(
+            stdout,
+            "\x1b[33mNOTE:\x1b[0m Base {} can not be resolved here, the whole file is searched\x1b[0K\r\n",
+            patch.base
+        )
+        .unwrap();
+    }
+
+    // patches apply to a single slice of a fat Mach-O binary
+    let mut range = 0..data.len();
+    if settings.sec_key[0] != "ACPI" {
+        let arch = match patch.arch.as_str() {
+            "i386" => "i386",
+            _ => "x86_64",
+        };
+        match macho::slice_range(&data, arch) {
+            Some(r) => {
+                if r.len() != data.len() {
+                    write!(
+                        stdout,
+                        "searching {} slice at {:#x} - {:#x}\x1b[0K\r\n",
+                        arch, r.start, r.end
+                    )
+                    .unwrap();
+                }
+                range = r;
+            }
+            None => {
+                write!(
+                    stdout,
+                    "\x1b[31mERROR: \x1b[0mno {} slice found in {:?}\x1b[0K\r\n\x1b[2K",
+                    arch, file_name
+                )
+                .unwrap();
+                return;
+            }
+        }
+    }
+
+    let outcome = apply(&patch, &mut data, range);
+    write!(
+        stdout,
+        "\x1b[32mFound\x1b[0m {} match{} of Find, \x1b[32mpatched\x1b[0m {}\x1b[0K\r\n",
+        outcome.found.len(),
+        if outcome.found.len() == 1 { "" } else { "es" },
+        outcome.patched.len(),
+    )
+    .unwrap();
+    for off in outcome.found.iter().take(MAX_LISTED) {
+        write!(
+            stdout,
+            "  {:#010x} {}\x1b[0K\r\n",
+            off,
+            if outcome.patched.contains(off) {
+                "\x1b[32mpatched\x1b[0m"
+            } else {
+                "\x1b[33mskipped\x1b[0m"
+            }
+        )
+        .unwrap();
+    }
+    if outcome.found.len() > MAX_LISTED {
+        write!(
+            stdout,
+            "  ... and {} more\x1b[0K\r\n",
+            outcome.found.len() - MAX_LISTED
+        )
+        .unwrap();
+    }
+    if outcome.patched.is_empty() {
+        write!(
+            stdout,
+            "\x1b[33mWARNING:\x1b[0m patch has no effect on this file\x1b[0K\r\n\x1b[2K"
+        )
+        .unwrap();
+        return;
+    }
+
+    let mut out_name = file_name.file_name().unwrap().to_os_string();
+    out_name.push("_patched");
+    let out_path = file_name.with_file_name(out_name);
+    write!(
+        stdout,
+        "\r\n\x1b[4mPress\x1b[0m '\x1b[32mw\x1b[0m' to write the patched copy to {:?}, any other key to \
+         continue\x1b[0K\r\n\x1b[2K",
+        out_path
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    if read_key().unwrap().0 == KeyCode::Char('w') {
+        match fs::write(&out_path, &data) {
+            Ok(_) => write!(stdout, "\x1b[32mDone\x1b[0m\x1b[0K\r\n\x1b[2K").unwrap(),
+            Err(e) => write!(
+                stdout,
+                "\x1b[31mERROR: \x1b[0mcould not write {:?}: {}\x1b[0K\r\n\x1b[2K",
+                out_path, e
+            )
+            .unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(find: &[u8], replace: &[u8]) -> Patch {
+        Patch {
+            find: find.to_vec(),
+            replace: replace.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn replaces_every_match() {
+        let mut data = b"xABxABABx".to_vec();
+        let len = data.len();
+        let outcome = apply(&patch(b"AB", b"CD"), &mut data, 0..len);
+        assert_eq!(outcome.found, vec![1, 4, 6]);
+        assert_eq!(outcome.patched, vec![1, 4, 6]);
+        assert_eq!(data, b"xCDxCDCDx");
+    }
+
+    #[test]
+    fn matches_do_not_overlap() {
+        let mut data = b"AAAAA".to_vec();
+        let outcome = apply(&patch(b"AA", b"BB"), &mut data, 0..5);
+        assert_eq!(outcome.found, vec![0, 2]);
+        assert_eq!(data, b"BBBBA");
+    }
+
+    #[test]
+    fn skips_and_counts() {
+        let mut data = b"ABABABAB".to_vec();
+        let p = Patch {
+            skip: 1,
+            count: 2,
+            ..patch(b"AB", b"CD")
+        };
+        let outcome = apply(&p, &mut data, 0..8);
+        assert_eq!(outcome.found, vec![0, 2, 4, 6]);
+        assert_eq!(outcome.patched, vec![2, 4]);
+        assert_eq!(data, b"ABCDCDAB");
+    }
+
+    #[test]
+    fn skipped_match_is_not_searched_again() {
+        // after skipping AA at 0 the search goes on from 2, not 1
+        let mut data = b"AAAB".to_vec();
+        let p = Patch {
+            skip: 1,
+            ..patch(b"AA", b"CC")
+        };
+        assert!(apply(&p, &mut data, 0..4).patched.is_empty());
+        assert_eq!(data, b"AAAB");
+    }
+
+    #[test]
+    fn applies_masks() {
+        let mut data = vec![0x12, 0x34, 0x1f, 0x56];
+        let p = Patch {
+            mask: vec![0xf0],
+            replace_mask: vec![0x0f],
+            ..patch(&[0x10], &[0xab])
+        };
+        let outcome = apply(&p, &mut data, 0..4);
+        assert_eq!(outcome.found, vec![0, 2]);
+        assert_eq!(data, vec![0x1b, 0x34, 0x1b, 0x56]);
+    }
+
+    #[test]
+    fn searches_range_and_limit() {
+        let mut data = b"ABABABAB".to_vec();
+        assert_eq!(
+            apply(&patch(b"AB", b"CD"), &mut data, 2..6).patched,
+            vec![2, 4]
+        );
+        let p = Patch {
+            limit: 3,
+            ..patch(b"AB", b"EF")
+        };
+        assert!(apply(&p, &mut data, 4..8).patched.is_empty());
+        let p = Patch { limit: 4, ..p };
+        assert_eq!(apply(&p, &mut data, 4..8).patched, vec![6]);
+    }
+
+    #[test]
+    fn checks_lengths() {
+        assert!(patch(b"AB", b"CD").check().is_ok());
+        assert!(patch(b"", b"").check().is_err());
+        assert!(patch(b"AB", b"C").check().is_err());
+        let p = Patch {
+            mask: vec![0xff],
+            ..patch(b"AB", b"CD")
+        };
+        assert!(p.check().is_err());
+    }
+}
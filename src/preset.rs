@@ -0,0 +1,227 @@
+use crate::edit::{edit_string, read_key};
+use crate::hw::{self, Profile};
+use crate::init::Settings;
+use crate::res::Resources;
+
+use crossterm::{cursor, event::KeyCode};
+use plist::Value;
+
+use std::io::{Stdout, Write};
+
+/// file in tool_config_files holding the quirk presets
+pub const PRESETS_FILE: &str = "tool_config_files/quirk_presets.json";
+
+/// a value the preset sets and what the loaded config.plist has there now
+struct Change {
+    path: [String; 3],  // section, sub section, key
+    old: Option<Value>, // None if the key is not in the config.plist
+    new: Value,
+}
+
+/// ids of the presets made for the CPU generation and form factor of `profile`
+pub fn matching(resources: &Resources, profile: &Profile) -> Vec<String> {
+    let generation = match profile.cpu_generation() {
+        Some((id, _)) => id,
+        None => return vec![],
+    };
+    let mut ids = vec![];
+    if let Some(presets) = resources.quirk_presets["quirk_presets"].as_object() {
+        for (id, preset) in presets {
+            let cpu_matches = preset["cpu"]
+                .as_array()
+                .map(|c| c.iter().any(|g| g.as_str() == Some(generation)))
+                .unwrap_or(false);
+            let laptop_matches = preset["laptop"]
+                .as_bool()
+                .map(|l| l == profile.laptop)
+                .unwrap_or(true);
+            if cpu_matches && laptop_matches {
+                ids.push(id.to_owned());
+            }
+        }
+    }
+    ids
+}
+
+/// pick a quirk preset, show what it would change in the loaded config.plist, then
+/// apply it if confirmed
+pub fn apply_preset(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    let presets = match resources.quirk_presets["quirk_presets"].as_object() {
+        Some(p) if !p.is_empty() => p.clone(),
+        _ => {
+            write!(
+                stdout,
+                "\r\n\x1b[31mERROR:\x1b[0m no quirk presets found in {}\x1b[0K\r\n",
+                PRESETS_FILE
+            )
+            .unwrap();
+            return;
+        }
+    };
+
+    let detected = matching(resources, &hw::detect(&resources.hw_root_path));
+    let mut choices = vec![];
+    for (id, preset) in &presets {
+        choices.push(format!(
+            "{} --- {}{}",
+            id,
+            preset["description"].as_str().unwrap_or(""),
+            if detected.contains(id) {
+                " \x1b[32m(matches this machine)\x1b[0m"
+            } else {
+                ""
+            }
+        ));
+    }
+    let mut selected = detected.first().cloned().unwrap_or_default();
+    write!(
+        stdout,
+        "\x1b[2K\r\n{}\r\x1B[2K\x1b[32mEnter or select the quirk preset to apply:\x1b[0m {}\r\n\x1B[2K\x1B8",
+        cursor::Show,
+        cursor::SavePosition,
+    )
+    .unwrap();
+    let new_val_set = edit_string(&mut selected, Some(&choices), stdout).unwrap();
+    write!(stdout, "{}\x1B[0J", cursor::Hide).unwrap();
+    if !new_val_set {
+        return;
+    }
+    let id = selected.trim();
+    let preset = match presets.get(id) {
+        Some(p) => p,
+        None => {
+            write!(
+                stdout,
+                "\r\n\x1b[31mERROR:\x1b[0m no quirk preset named {}\x1b[0K\r\n",
+                id
+            )
+            .unwrap();
+            return;
+        }
+    };
+
+    let values: Vec<(String, String, String, serde_json::Value)> =
+        serde_json::from_value(preset["values"].clone()).unwrap_or_default();
+    let mut changes = vec![];
+    let mut unchanged = 0;
+    for (sec, sub, key, val) in values {
+        let old = resources
+            .config_plist
+            .as_dictionary()
+            .and_then(|d| d.get(&sec))
+            .and_then(|s| s.as_dictionary())
+            .and_then(|s| s.get(&sub))
+            .and_then(|s| s.as_dictionary())
+            .and_then(|s| s.get(&key))
+            .cloned();
+        let new = match to_plist(&val, old.as_ref()) {
+            Some(v) => v,
+            None => continue,
+        };
+        if old.as_ref() == Some(&new) {
+            unchanged += 1;
+        } else {
+            changes.push(Change {
+                path: [sec, sub, key],
+                old,
+                new,
+            });
+        }
+    }
+
+    write!(
+        stdout,
+        "\r\n\x1b[4mQuirk preset\x1b[0m {} - {}\x1b[0K\r\n",
+        id,
+        preset["description"].as_str().unwrap_or("")
+    )
+    .unwrap();
+    let mut to_apply = 0;
+    for change in &changes {
+        let path = change.path.join("->");
+        match &change.old {
+            Some(old) => {
+                to_apply += 1;
+                write!(
+                    stdout,
+                    "  \x1b[33m{:<45}\x1b[0m {} -> \x1b[32m{}\x1b[0m\x1b[0K\r\n",
+                    path,
+                    show(old),
+                    show(&change.new)
+                )
+                .unwrap();
+            }
+            None => write!(
+                stdout,
+                "  \x1b[31m{:<45}\x1b[0m not in config.plist, skipped\x1b[0K\r\n",
+                path
+            )
+            .unwrap(),
+        }
+    }
+    write!(
+        stdout,
+        "\r\n{} values already match the preset\x1b[0K\r\n",
+        unchanged
+    )
+    .unwrap();
+    if to_apply == 0 {
+        write!(stdout, "\x1b[32mNothing to change\x1b[0m\x1b[0K\r\n").unwrap();
+        return;
+    }
+    write!(
+        stdout,
+        "\x1b[4mPress\x1b[0m '\x1b[32my\x1b[0m' to apply the {} changes, any other key to cancel\x1b[0K\r\n\x1b[2K",
+        to_apply
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    if read_key().unwrap().0 != KeyCode::Char('y') {
+        return;
+    }
+    for change in changes.into_iter().filter(|c| c.old.is_some()) {
+        let [sec, sub, key] = change.path;
+        if let Some(section) = resources
+            .config_plist
+            .as_dictionary_mut()
+            .and_then(|d| d.get_mut(&sec))
+            .and_then(|s| s.as_dictionary_mut())
+            .and_then(|s| s.get_mut(&sub))
+            .and_then(|s| s.as_dictionary_mut())
+        {
+            section.insert(key, change.new);
+        }
+    }
+    settings.modified = true;
+    write!(
+        stdout,
+        "\x1b[32mApplied\x1b[0m {} changes from {}\x1b[0K\r\n",
+        to_apply, id
+    )
+    .unwrap();
+}
+
+/// convert a preset value to the plist type of the value it replaces
+/// strings replacing data are read as hex, e.g. Cpuid1Data
+fn to_plist(val: &serde_json::Value, old: Option<&Value>) -> Option<Value> {
+    match val {
+        serde_json::Value::Bool(b) => Some(Value::Boolean(*b)),
+        serde_json::Value::Number(n) => Some(Value::Integer(n.as_i64()?.into())),
+        serde_json::Value::String(s) => match old {
+            Some(Value::Data(_)) => hex::decode(s).ok().map(Value::Data),
+            _ => Some(Value::String(s.to_owned())),
+        },
+        _ => None,
+    }
+}
+
+/// short display form of a plist value
+fn show(val: &Value) -> String {
+    match val {
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        Value::Data(d) => format!("<{}>", hex::encode_upper(d)),
+        _ => "...".to_string(),
+    }
+}
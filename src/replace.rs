@@ -0,0 +1,258 @@
+use crate::edit::{hex_matches, prompt, read_key, search_regex, value_match, value_texts};
+use crate::init::Settings;
+use crate::res::Resources;
+
+use crossterm::event::KeyCode;
+use plist::Value;
+use regex::{NoExpand, Regex};
+
+use std::io::{Stdout, Write};
+
+/// a value that matched the search and what it would be replaced with
+struct Hit {
+    path: Vec<String>, // raw keys from the top of the plist, array items by number
+    old: Value,
+    new: Value,
+}
+
+/// ask for a search term, a replacement and where to replace, then go through every
+/// string, integer and data value whose key or value matches asking before each change
+/// matched parts of a value are replaced, values found by their key are set to the
+/// replacement, e.g. searching for MaxKernel and replacing with 21.99.99
+pub fn replace(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    let mut term = String::new();
+    if !prompt(
+        "Enter search term or /regex/ to replace:",
+        &mut term,
+        stdout,
+    ) || term.is_empty()
+    {
+        return;
+    }
+    let search = match search_regex(&term) {
+        Ok(s) => s,
+        Err(e) => {
+            write!(stdout, "\r\n\x1b[31mERROR:\x1b[0m {}\x1b[0K\r\n", e).unwrap();
+            return;
+        }
+    };
+    // capture groups like $1 only mean something in a regex
+    let is_regex = term.len() > 2 && term.starts_with('/') && term.ends_with('/');
+    let mut with = String::new();
+    if !prompt("Replace with:", &mut with, stdout) {
+        return;
+    }
+
+    let section = settings.raw_sec_key[..=settings.depth].to_vec();
+    write!(
+        stdout,
+        "\r\n\x1b[2KReplace in \x1b[32ma\x1b[0mll of the config.plist or only in \x1b[32ms\x1b[0m {}, \
+         any other key to cancel\x1b[0K\r\n\x1b[2K",
+        settings.sec_key[..=settings.depth].join("->")
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    let mut path = match read_key().unwrap().0 {
+        KeyCode::Char('a') => vec![],
+        KeyCode::Char('s') => section,
+        _ => return,
+    };
+    let start = match value_at(&mut resources.config_plist, &path) {
+        Some(v) => v.clone(),
+        None => return,
+    };
+    let mut hits = vec![];
+    find_hits(&search, &with, is_regex, &start, &mut path, &mut hits);
+    if hits.is_empty() {
+        write!(stdout, "\x1b[33mNothing to replace\x1b[0m\x1b[0K\r\n").unwrap();
+        return;
+    }
+
+    let mut replaced = 0;
+    let mut all = false;
+    for (i, hit) in hits.iter().enumerate() {
+        if !all {
+            write!(
+                stdout,
+                "\r\n\x1b[33m{}/{}\x1b[0m {}\x1b[0K\r\n  {} -> \x1b[32m{}\x1b[0m\x1b[0K\r\n\
+                 '\x1b[32my\x1b[0m' replace, '\x1b[32mn\x1b[0m' skip, '\x1b[32mA\x1b[0m' replace \
+                 this and the rest, Esc stop\x1b[0K\r\n\x1b[2K",
+                i + 1,
+                hits.len(),
+                hit.path.join("->"),
+                show(&hit.old),
+                show(&hit.new)
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+            match read_key().unwrap().0 {
+                KeyCode::Char('y') => (),
+                KeyCode::Char('A') => all = true,
+                KeyCode::Esc | KeyCode::Char('q') => break,
+                _ => continue,
+            }
+        }
+        if let Some(v) = value_at(&mut resources.config_plist, &hit.path) {
+            *v = hit.new.clone();
+            replaced += 1;
+        }
+    }
+    if replaced > 0 {
+        settings.modified = true;
+    }
+    write!(
+        stdout,
+        "\r\n\x1b[32mReplaced\x1b[0m {} of {} matches\x1b[0K\r\n",
+        replaced,
+        hits.len()
+    )
+    .unwrap();
+}
+
+/// the value at `path` in `plist`, array items by number
+fn value_at<'a>(plist: &'a mut Value, path: &[String]) -> Option<&'a mut Value> {
+    let mut val = plist;
+    for key in path {
+        val = match val {
+            Value::Dictionary(d) => d.get_mut(key)?,
+            Value::Array(a) => a.get_mut(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(val)
+}
+
+/// gather the values below `val` that would change, `path` is where `val` is
+fn find_hits(
+    search: &Regex,
+    with: &str,
+    is_regex: bool,
+    val: &Value,
+    path: &mut Vec<String>,
+    hits: &mut Vec<Hit>,
+) {
+    match val {
+        Value::Dictionary(d) => {
+            for (k, v) in d {
+                path.push(k.to_owned());
+                find_hits(search, with, is_regex, v, path, hits);
+                path.pop();
+            }
+        }
+        Value::Array(a) => {
+            for (i, v) in a.iter().enumerate() {
+                path.push(i.to_string());
+                find_hits(search, with, is_regex, v, path, hits);
+                path.pop();
+            }
+        }
+        _ => {
+            let key_matches = path
+                .last()
+                .map(|k| k.parse::<usize>().is_err() && search.is_match(k))
+                .unwrap_or(false);
+            if let Some(new) = replaced(search, with, is_regex, val, key_matches) {
+                if &new != val {
+                    hits.push(Hit {
+                        path: path.clone(),
+                        old: val.clone(),
+                        new,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// `val` with the parts matching `search` replaced, or set to `with` if only its key
+/// matched, None if neither matched or the result isn't valid for its type
+fn replaced(
+    search: &Regex,
+    with: &str,
+    is_regex: bool,
+    val: &Value,
+    key_matches: bool,
+) -> Option<Value> {
+    let texts = value_texts(val);
+    let matched = value_match(search, val);
+    if matched.is_none() && !key_matches {
+        return None;
+    }
+    let new_text = |text: &str| -> String {
+        if is_regex {
+            search.replace_all(text, with).to_string()
+        } else {
+            search.replace_all(text, NoExpand(with)).to_string()
+        }
+    };
+    match (val, matched) {
+        (Value::String(s), Some(_)) => Some(Value::String(new_text(s))),
+        (Value::String(_), None) => Some(Value::String(with.to_owned())),
+        (Value::Integer(_), Some(_)) => Some(Value::Integer(
+            new_text(&texts[0]).parse::<i64>().ok()?.into(),
+        )),
+        (Value::Integer(_), None) => Some(Value::Integer(with.parse::<i64>().ok()?.into())),
+        // data matched as hex is replaced in its hex form, as ASCII in its text
+        (Value::Data(_), Some(0)) => {
+            hex::decode(replace_hex(search, &texts[0], with, is_regex).replace(' ', ""))
+                .ok()
+                .map(Value::Data)
+        }
+        (Value::Data(d), Some(_)) => {
+            std::str::from_utf8(d).ok()?;
+            Some(Value::Data(new_text(&texts[1]).into_bytes()))
+        }
+        (Value::Data(_), None) => hex::decode(with.replace(' ', "")).ok().map(Value::Data),
+        _ => None,
+    }
+}
+
+/// `hex` with only the matches of `search` that cover whole bytes replaced
+fn replace_hex(search: &Regex, hex: &str, with: &str, is_regex: bool) -> String {
+    let mut new = String::new();
+    let mut last = 0;
+    for m in hex_matches(search, hex) {
+        new.push_str(&hex[last..m.start]);
+        match search.captures_at(hex, m.start) {
+            Some(caps) if is_regex => caps.expand(with, &mut new),
+            _ => new.push_str(with),
+        }
+        last = m.end;
+    }
+    new.push_str(&hex[last..]);
+    new
+}
+
+/// short display form of a plist value
+fn show(val: &Value) -> String {
+    match val {
+        Value::Integer(i) => i.to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        Value::Data(d) => format!("<{}>", hex::encode_upper(d)),
+        _ => "...".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_is_replaced_on_whole_bytes() {
+        let search = search_regex("AB").unwrap();
+        let data = Value::Data(vec![0x0A, 0xB0, 0xAB]);
+        assert_eq!(
+            replaced(&search, "CD", false, &data, false),
+            Some(Value::Data(vec![0x0A, 0xB0, 0xCD]))
+        );
+        assert_eq!(
+            replaced(&search, "CD", false, &Value::Data(vec![0x0A, 0xB0]), false),
+            None
+        );
+        let groups = search_regex("/(A)(B)/").unwrap();
+        assert_eq!(
+            replaced(&groups, "$2$1", true, &Value::Data(vec![0xAB]), false),
+            Some(Value::Data(vec![0xBA]))
+        );
+    }
+}
@@ -1,11 +1,14 @@
+use crate::acpi;
 use crate::edit;
 use crate::init::{Manifest, Settings};
+use crate::macho;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufReader, Read, Stdout, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output};
 
+use crossterm::event::KeyCode;
 use crossterm::terminal::size;
 use crossterm::{cursor, terminal, ExecutableCommand};
 use curl::easy::Easy;
@@ -24,14 +27,17 @@ pub struct Resources {
     pub dortania: serde_json::Value, // Dortania builds config.json file
     pub octool_config: serde_json::Value, // config file for octool itself
     pub config_differences: serde_json::Value, // config file for octool itself
+    pub quirk_presets: serde_json::Value, // Booter/Kernel/UEFI quirk sets per platform
     pub resource_list: serde_json::Value, // list linking resources to their parents
     pub other: serde_json::Value,    // list of other party parent/childs
     pub config_plist: plist::Value,  // current active config.plist
     pub sample_plist: plist::Value,  // latest Sample.plist
+    pub schema: serde_json::Value,   // key types and values from Configuration.tex
     pub input_dir_path: PathBuf,     // location of INPUT directory to be used
     pub working_dir_path: PathBuf,   // location of octool and files
     pub open_core_binaries_path: PathBuf, // location of the OpenCorePkg binaries
     pub open_core_source_path: PathBuf, // location of OpenCore source files
+    pub hw_root_path: PathBuf,       // root of the sys and proc trees used for hardware detection
 }
 
 /// get list of available version numbers for parent resource in the dortania build config.json
@@ -461,7 +467,7 @@ pub fn show_res_info(resources: &mut Resources, settings: &mut Settings, stdout:
                     write!(stdout, "{:?}\x1b[0K\r\n", outpath).unwrap();
                     let respath = resources
                         .working_dir_path
-                        .join(outpath)
+                        .join(&outpath)
                         .join("Contents/Info.plist");
                     if respath.exists() {
                         let info =
@@ -501,6 +507,18 @@ pub fn show_res_info(resources: &mut Resources, settings: &mut Settings, stdout:
                             }
                         }
                     }
+                    if settings.sec_key[0] == "ACPI" {
+                        let table = resources.working_dir_path.join(&outpath);
+                        show_acpi_header_info(resources, settings, &table, stdout);
+                    }
+                    if settings.sec_key[0] == "Kernel" {
+                        show_kext_binary_info(
+                            resources,
+                            settings,
+                            &resources.working_dir_path.join(&outpath),
+                            stdout,
+                        );
+                    }
                 }
                 _ => write!(
                     stdout,
@@ -519,6 +537,148 @@ pub fn show_res_info(resources: &mut Resources, settings: &mut Settings, stdout:
     .unwrap();
 }
 
+/// Show the header of the ACPI table and warn if it is not a valid table
+/// if the Comment of the ACPI > Add entry is empty offer to fill it with the table ID
+fn show_acpi_header_info(
+    resources: &mut Resources,
+    settings: &mut Settings,
+    table: &Path,
+    stdout: &mut Stdout,
+) {
+    write!(stdout, "\x1b[2K\r\n").unwrap();
+    let header = match acpi::inspect(table) {
+        Ok(h) => h,
+        Err(e) => {
+            write!(stdout, "\x1b[31mERROR:\x1b[0m {}\x1b[0K\r\n", e).unwrap();
+            return;
+        }
+    };
+    write!(
+        stdout,
+        "\x1b[7mtable\x1b[0m  {} length {} revision {}\x1b[0K\r\n\
+         \x1b[7mOEM\x1b[0m    {} table ID {} revision {:#x}\x1b[0K\r\n\
+         \x1b[7mcreator\x1b[0m {} revision {:#x}\x1b[0K\r\n",
+        header.signature,
+        header.length,
+        header.revision,
+        header.oem_id,
+        header.oem_table_id,
+        header.oem_revision,
+        header.creator_id,
+        header.creator_revision,
+    )
+    .unwrap();
+    if header.checksum_valid() {
+        write!(
+            stdout,
+            "\x1b[7mchecksum\x1b[0m {:#04x} \x1b[32mvalid\x1b[0m\x1b[0K\r\n",
+            header.checksum
+        )
+        .unwrap();
+    } else {
+        write!(
+            stdout,
+            "\x1b[33mWARNING:\x1b[0m checksum {:#04x} is invalid, should be {:#04x}\x1b[0K\r\n",
+            header.checksum, header.expected_checksum
+        )
+        .unwrap();
+    }
+
+    if let Some(Value::Dictionary(entry)) = resources
+        .config_plist
+        .as_dictionary_mut()
+        .and_then(|d| d.get_mut("ACPI"))
+        .and_then(|a| a.as_dictionary_mut())
+        .and_then(|a| a.get_mut("Add"))
+        .and_then(|a| a.as_array_mut())
+        .and_then(|a| a.get_mut(settings.sec_num[2]))
+    {
+        if let Some(Value::String(c)) = entry.get_mut("Comment") {
+            if c.is_empty() {
+                write!(
+                    stdout,
+                    "\x1b[33mComment\x1b[0m is empty, press 'y' to set it to \x1b[4m{}\x1b[0m, \
+                     any other key to leave it\x1b[0K\r\n",
+                    header.comment()
+                )
+                .unwrap();
+                stdout.flush().unwrap();
+                if edit::read_key().unwrap().0 == KeyCode::Char('y') {
+                    *c = header.comment();
+                    settings.modified = true;
+                    write!(stdout, "\x1b[32mSet\x1b[0m Comment to {}\x1b[0K\r\n", c).unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// Show the architectures, minimum macOS and UUID of the kext executable and warn
+/// if the Kernel > Add entry settings don't allow it to load
+fn show_kext_binary_info(
+    resources: &Resources,
+    settings: &Settings,
+    kext: &Path,
+    stdout: &mut Stdout,
+) {
+    let entry = match resources.config_plist.as_dictionary().and_then(|d| {
+        d.get("Kernel")?
+            .as_dictionary()?
+            .get("Add")?
+            .as_array()?
+            .get(settings.sec_num[2])?
+            .as_dictionary()
+    }) {
+        Some(e) => e,
+        None => return,
+    };
+    let get = |key: &str| entry.get(key).and_then(|v| v.as_string()).unwrap_or("");
+    let mut exe = get("ExecutablePath").to_owned();
+    if exe.is_empty() {
+        if let Ok(info) = Value::from_file(kext.join(get("PlistPath"))) {
+            if let Some(name) = info
+                .as_dictionary()
+                .and_then(|d| d.get("CFBundleExecutable"))
+                .and_then(|n| n.as_string())
+            {
+                exe = format!("Contents/MacOS/{}", name);
+            }
+        }
+    }
+    if exe.is_empty() || !kext.join(&exe).exists() {
+        write!(
+            stdout,
+            "\x1b[2K\r\nno executable found, plist only kext\x1b[0K\r\n"
+        )
+        .unwrap();
+        return;
+    }
+    write!(stdout, "\x1b[2K\r\n").unwrap();
+    match macho::inspect(&kext.join(&exe)) {
+        Ok(slices) => {
+            for slice in &slices {
+                write!(
+                    stdout,
+                    "\x1b[7march\x1b[0m  {:<7} min macOS {:<9} UUID {}\x1b[0K\r\n",
+                    slice.arch,
+                    slice.min_os_string(),
+                    slice.uuid.as_deref().unwrap_or("none"),
+                )
+                .unwrap();
+            }
+            for warning in macho::kext_warnings(&slices, get("Arch"), get("MaxKernel")) {
+                write!(stdout, "\x1b[33mWARNING:\x1b[0m {}\x1b[0K\r\n", warning).unwrap();
+            }
+        }
+        Err(e) => write!(
+            stdout,
+            "\x1b[33mWARNING:\x1b[0m could not read {}: {}\x1b[0K\r\n",
+            exe, e
+        )
+        .unwrap(),
+    }
+}
+
 /// Read the `path` file into a `serde_json::Value`
 pub fn get_serde_json_quiet(path: &str) -> Result<serde_json::Value, Box<dyn Error>> {
     let file = File::open(Path::new(path))?;
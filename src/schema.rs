@@ -0,0 +1,208 @@
+use crate::parse_tex;
+
+use plist::Value;
+use serde_json::json;
+
+use std::fs;
+use std::path::Path;
+
+/// name of the cached schema, saved next to the Configuration.tex it was made from
+pub const SCHEMA_FILE: &str = "octool_schema.json";
+
+/// read the schema for the Configuration.tex in `docs_dir`, using the cached JSON if
+/// it is newer than the .tex file, otherwise build it from the .tex and cache it
+/// returns Null if there is no Configuration.tex
+pub fn load(docs_dir: &Path) -> serde_json::Value {
+    let tex_path = docs_dir.join("Configuration.tex");
+    let cache_path = docs_dir.join(SCHEMA_FILE);
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    if let (Some(tex), Some(cache)) = (modified(&tex_path), modified(&cache_path)) {
+        if cache >= tex {
+            if let Ok(schema) = crate::res::get_serde_json_quiet(cache_path.to_str().unwrap()) {
+                return schema;
+            }
+        }
+    }
+    let contents = match fs::read_to_string(&tex_path) {
+        Ok(c) => c,
+        Err(_) => return serde_json::Value::Null,
+    };
+    let schema = json!({ "fields": extract(&contents) });
+    let _ = fs::write(&cache_path, serde_json::to_string_pretty(&schema).unwrap());
+    schema
+}
+
+/// go through the Configuration.tex `contents` and gather the Type, Failsafe and
+/// listed valid values of every key, keyed by section>sub section>key
+/// keys in the top Properties subsection of a section have an empty sub section
+pub fn extract(contents: &str) -> serde_json::Map<String, serde_json::Value> {
+    let mut fields = serde_json::Map::new();
+    let mut section = String::new();
+    let mut sub = String::new();
+    let mut pending = String::new(); // last \texttt{Key}\\ line, the key if a Type follows
+    let mut field: Option<(String, serde_json::Value)> = None;
+    let mut valid_values = false; // "Valid values" seen in the current field
+    let mut itemize = 0;
+
+    let mut finish = |field: &mut Option<(String, serde_json::Value)>| {
+        if let Some((key, val)) = field.take() {
+            fields.entry(key).or_insert(val);
+        }
+    };
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('%') {
+            continue;
+        }
+        if let Some(title) = braced(line, "\\section{") {
+            finish(&mut field);
+            section = title;
+            sub.clear();
+            continue;
+        }
+        if let Some(title) = braced(line, "\\subsection{").or(braced(line, "\\subsubsection{")) {
+            finish(&mut field);
+            sub = title.trim_end_matches("Properties").trim().to_string();
+            continue;
+        }
+        if line.contains("\\begin{itemize}") {
+            itemize += 1;
+            continue;
+        }
+        if line.contains("\\end{itemize}") {
+            itemize -= 1;
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("\\textbf{Type}:") {
+            if !pending.is_empty() {
+                finish(&mut field);
+                let kind = clean(rest);
+                let kind = kind
+                    .trim_start_matches("plist")
+                    .split([' ', ','])
+                    .find(|w| !w.is_empty())
+                    .unwrap_or("")
+                    .to_string();
+                field = Some((
+                    format!("{}>{}>{}", section, sub, pending),
+                    json!({ "type": kind, "failsafe": "", "values": [] }),
+                ));
+                valid_values = false;
+                pending.clear();
+            }
+            continue;
+        }
+        let body = line.strip_prefix("\\item").unwrap_or(line).trim();
+        if body.starts_with("\\texttt{") && body.ends_with("\\\\") {
+            if let Some(key) = braced(body, "\\texttt{") {
+                pending = clean(&key);
+            }
+            continue;
+        }
+        let (_, val) = match field.as_mut() {
+            Some(f) => f,
+            None => continue,
+        };
+        if let Some(rest) = line.strip_prefix("\\textbf{Failsafe}:") {
+            val["failsafe"] = json!(clean(rest));
+        } else if line.to_lowercase().contains("valid values") {
+            valid_values = true;
+        } else if valid_values && itemize == 1 && line.starts_with("\\item") && line.contains("---")
+        {
+            if let Some(v) = braced(body, "\\texttt{") {
+                val["values"].as_array_mut().unwrap().push(json!(clean(&v)));
+            }
+        }
+    }
+    finish(&mut field);
+    fields
+}
+
+/// schema entry for the key at `path`, the raw keys from the section down
+/// e.g. [Misc, Security, Vault] or [Kernel, Add, 0, BundlePath]
+pub fn lookup<'a>(schema: &'a serde_json::Value, path: &[String]) -> Option<&'a serde_json::Value> {
+    let fields = schema["fields"].as_object()?;
+    let (sec, field) = (path.first()?, path.last()?);
+    if path.len() < 2 || field.starts_with('#') {
+        return None;
+    }
+    // nearest named parent first, array indexes are skipped
+    for parent in path[1..path.len() - 1]
+        .iter()
+        .rev()
+        .filter(|p| p.parse::<usize>().is_err())
+    {
+        if let Some(f) = fields.get(&format!("{}>{}>{}", sec, parent, field)) {
+            return Some(f);
+        }
+    }
+    if let Some(f) = fields.get(&format!("{}>>{}", sec, field)) {
+        return Some(f);
+    }
+    // e.g. Misc > Entries items are documented under Entry Properties
+    let prefix = format!("{}>", sec);
+    let suffix = format!(">{}", field);
+    let mut found = fields
+        .iter()
+        .filter(|(k, _)| k.starts_with(&prefix) && k.ends_with(&suffix));
+    match (found.next(), found.next()) {
+        (Some((_, f)), None) if path.len() > 2 => Some(f),
+        _ => None,
+    }
+}
+
+/// check `val` against its schema entry, returns a description of the problem and
+/// true if it is the wrong type rather than an unlisted value
+pub fn check(entry: &serde_json::Value, val: &Value) -> Option<(String, bool)> {
+    let kind = entry["type"].as_str().unwrap_or("");
+    let actual = match val {
+        Value::Array(_) => "array",
+        Value::Dictionary(_) => "dict",
+        Value::Boolean(_) => "boolean",
+        Value::Data(_) => "data",
+        Value::Integer(_) => "integer",
+        Value::String(_) => "string",
+        _ => return None,
+    };
+    // multidata can hold any type
+    if kind.is_empty() || kind == "multidata" {
+        return None;
+    }
+    if kind != actual {
+        return Some((format!("should be {}", kind), true));
+    }
+    if let (Value::String(s), Some(values)) = (val, entry["values"].as_array()) {
+        if values.len() > 1 && !values.iter().any(|v| v.as_str() == Some(s)) {
+            return Some(("not a listed value".to_string(), false));
+        }
+    }
+    None
+}
+
+/// text of the first {} group after `start` in `line`
+fn braced(line: &str, start: &str) -> Option<String> {
+    let rest = &line[line.find(start)? + start.len()..];
+    let mut depth = 1;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(rest[..i].to_string());
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// tex with the formatting removed and spaces collapsed
+fn clean(tex: &str) -> String {
+    parse_tex::parse_line(tex, 0, 0, false, true, false)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
@@ -0,0 +1,392 @@
+use crate::acpi;
+use crate::edit::read_key;
+use crate::init::Settings;
+use crate::res::{self, Resources};
+
+use crossterm::event::KeyCode;
+use plist::Value;
+use walkdir::WalkDir;
+
+use std::io::{Stdout, Write};
+use std::path::{Path, PathBuf};
+
+/// files found by a snapshot scan, sorted by the config section they belong in
+#[derive(Debug, Default)]
+struct Found {
+    acpi: Vec<String>,            // .aml paths relative to ACPI folder
+    kexts: Vec<(String, String)>, // BundlePath, ExecutablePath
+    drivers: Vec<String>,         // driver .efi names
+    tools: Vec<String>,           // tool .efi names
+    unknown: Vec<String>,         // .efi files we can't place
+}
+
+/// scan the INPUT folder or the built OUTPUT/EFI/OC folder for resources and
+/// update the ACPI > Add, Kernel > Add, UEFI > Drivers and Misc > Tools sections
+/// missing entries are added from the Sample.plist templates, entries whose files
+/// can't be found are listed, then the kext order is checked and fixed
+pub fn snapshot(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    let input_name = resources
+        .input_dir_path
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+    write!(
+        stdout,
+        "\r\n\x1b[2K\x1b[4mSnapshot\x1b[0m resources from '\x1b[32mi\x1b[0m' {} folder or \
+         '\x1b[32mo\x1b[0m' OUTPUT/EFI/OC folder, any other key to cancel\x1b[0K\r\n\x1b[2K",
+        input_name,
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    let from_output = match read_key().unwrap().0 {
+        KeyCode::Char('i') => false,
+        KeyCode::Char('o') => true,
+        _ => return,
+    };
+
+    let found = if from_output {
+        let oc = Path::new("OUTPUT/EFI/OC");
+        if !oc.exists() {
+            write!(
+                stdout,
+                "\x1b[31mERROR:\x1b[0m OUTPUT/EFI/OC not found, build it first with 'G'\x1b[0K\r\n"
+            )
+            .unwrap();
+            return;
+        }
+        scan_output(oc)
+    } else {
+        scan_input(&resources.input_dir_path, resources)
+    };
+
+    let mut added = 0;
+    let bundles: Vec<String> = found.kexts.iter().map(|k| k.0.to_owned()).collect();
+    for (section, names) in [
+        (("ACPI", "Add", "Path"), &found.acpi),
+        (("Kernel", "Add", "BundlePath"), &bundles),
+        (("UEFI", "Drivers", "Path"), &found.drivers),
+        (("Misc", "Tools", "Path"), &found.tools),
+    ] {
+        let new_items = update_section(resources, section, names, from_output, stdout);
+        added += new_items.len();
+        if section.0 == "ACPI" {
+            // use the table ID of the newly added tables as their Comment
+            let acpi_dir = if from_output {
+                PathBuf::from("OUTPUT/EFI/OC/ACPI")
+            } else {
+                resources.input_dir_path.clone()
+            };
+            if let Some(Value::Array(tables)) = section_mut(resources, "ACPI", "Add") {
+                for table in tables.iter_mut().filter_map(|t| t.as_dictionary_mut()) {
+                    let path = table
+                        .get("Path")
+                        .and_then(|p| p.as_string())
+                        .unwrap_or("")
+                        .to_owned();
+                    if new_items.contains(&path) {
+                        if let Ok(header) = acpi::inspect(&acpi_dir.join(&path)) {
+                            table.insert("Comment".to_string(), Value::String(header.comment()));
+                        }
+                    }
+                }
+            }
+        }
+        if section.0 == "Kernel" {
+            // fill in the executable paths of the newly added kexts
+            if let Some(Value::Array(kexts)) = section_mut(resources, "Kernel", "Add") {
+                for kext in kexts.iter_mut().filter_map(|k| k.as_dictionary_mut()) {
+                    let bundle = kext
+                        .get("BundlePath")
+                        .and_then(|b| b.as_string())
+                        .unwrap_or("")
+                        .to_owned();
+                    if new_items.contains(&bundle) {
+                        if let Some(k) = found.kexts.iter().find(|k| k.0 == bundle) {
+                            kext.insert(
+                                "ExecutablePath".to_string(),
+                                Value::String(k.1.to_owned()),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for efi in &found.unknown {
+        write!(
+            stdout,
+            "\r\n\x1b[33mUnknown\x1b[0m {} is not a known driver or tool, add it manually\x1b[0K",
+            efi
+        )
+        .unwrap();
+    }
+
+    if added > 0 {
+        settings.modified = true;
+        write!(
+            stdout,
+            "\r\n\r\n\x1b[2K\x1b[32mChecking\x1b[0m for missing requirements and wrong order\r\n"
+        )
+        .unwrap();
+        let mut order_attempts = 0;
+        while !res::check_order(settings, resources, stdout, false) {
+            order_attempts += 1;
+            if order_attempts > 10 {
+                break;
+            }
+        }
+    } else {
+        write!(
+            stdout,
+            "\r\n\x1b[33mNo additions made to config.plist\x1b[0m\x1b[0K"
+        )
+        .unwrap();
+    }
+    write!(stdout, "\r\n\x1b[2K").unwrap();
+    stdout.flush().unwrap();
+}
+
+/// add entries for `names` that are not in the `sec` > `sub` array and report the
+/// entries that have no file behind them, returns the names that were added
+fn update_section(
+    resources: &mut Resources,
+    (sec, sub, path_key): (&str, &str, &str),
+    names: &[String],
+    from_output: bool,
+    stdout: &mut Stdout,
+) -> Vec<String> {
+    let mut added = vec![];
+    let template = sample_template(resources, sec, sub);
+    let known: Vec<String> = resources
+        .resource_list
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(|k| k.to_owned())
+        .collect();
+    let section = match section_mut(resources, sec, sub) {
+        Some(Value::Array(a)) => a,
+        _ => return added,
+    };
+
+    let mut existing = vec![];
+    for item in section.iter() {
+        match item {
+            Value::Dictionary(d) => existing.push(
+                d.get(path_key)
+                    .and_then(|p| p.as_string())
+                    .unwrap_or("")
+                    .to_owned(),
+            ),
+            Value::String(s) => existing.push(s.trim_start_matches('#').to_owned()),
+            _ => (),
+        }
+    }
+
+    for name in names {
+        if existing.contains(name) {
+            continue;
+        }
+        let new_item = match &template {
+            Some(Value::Dictionary(t)) => {
+                let mut d = t.clone();
+                d.insert(path_key.to_string(), Value::String(name.to_owned()));
+                d.insert("Enabled".to_string(), Value::Boolean(true));
+                match sec {
+                    "Kernel" => {
+                        d.insert("Arch".to_string(), Value::String("Any".to_string()));
+                        d.insert(
+                            "PlistPath".to_string(),
+                            Value::String("Contents/Info.plist".to_string()),
+                        );
+                    }
+                    "Misc" => {
+                        d.insert("Flavour".to_string(), Value::String("Auto".to_string()));
+                    }
+                    _ => (),
+                }
+                Value::Dictionary(d)
+            }
+            // OpenCore 0.7.2 and below use plain strings for drivers
+            Some(Value::String(_)) => Value::String(name.to_owned()),
+            _ => continue,
+        };
+        section.push(new_item);
+        added.push(name.to_owned());
+        write!(
+            stdout,
+            "\r\n\x1b[7mAdded\x1b[0m {}->{}->{}\x1b[0K",
+            sec, sub, name
+        )
+        .unwrap();
+    }
+
+    for name in existing {
+        if name.is_empty() || names.contains(&name) {
+            continue;
+        }
+        // files octool knows about will be gathered when building, so they aren't gone
+        if !from_output && known.contains(&resource_name(&name)) {
+            continue;
+        }
+        write!(
+            stdout,
+            "\r\n\x1b[33mMissing\x1b[0m {}->{}->{} file not found\x1b[0K",
+            sec, sub, name
+        )
+        .unwrap();
+    }
+    stdout.flush().unwrap();
+    added
+}
+
+/// name of the resource that provides the file at `path`, the outer kext for a
+/// plugin kext, e.g. VoodooPS2Controller.kext/Contents/PlugIns/VoodooPS2Keyboard.kext
+fn resource_name(path: &str) -> String {
+    match path.split_once("/Contents/PlugIns/") {
+        Some((outer, _)) => outer.rsplit('/').next().unwrap().to_owned(),
+        None => path.rsplit('/').next().unwrap().to_owned(),
+    }
+}
+
+fn section_mut<'a>(resources: &'a mut Resources, sec: &str, sub: &str) -> Option<&'a mut Value> {
+    resources
+        .config_plist
+        .as_dictionary_mut()?
+        .get_mut(sec)?
+        .as_dictionary_mut()?
+        .get_mut(sub)
+}
+
+/// first item of the `sec` > `sub` array in the Sample.plist with its values cleared
+pub fn sample_template(resources: &Resources, sec: &str, sub: &str) -> Option<Value> {
+    let mut item = resources
+        .sample_plist
+        .as_dictionary()?
+        .get(sec)?
+        .as_dictionary()?
+        .get(sub)?
+        .as_array()?
+        .first()?
+        .to_owned();
+    clear_values(&mut item);
+    Some(item)
+}
+
+/// clear the values of an array item taken from the Sample.plist so it can be used
+/// as a template
+pub fn clear_values(item: &mut Value) {
+    if let Value::Dictionary(d) = item {
+        for val in d.values_mut() {
+            match val {
+                Value::String(_) => *val = Value::String("".to_string()),
+                Value::Boolean(_) => *val = Value::Boolean(false),
+                Value::Integer(_) => *val = Value::Integer(plist::Integer::from(0)),
+                Value::Data(_) => *val = Value::Data(Default::default()),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// INPUT folder holds resources at its top level, use resource_list.json to tell
+/// drivers from tools
+fn scan_input(input: &Path, resources: &Resources) -> Found {
+    let mut found = Found::default();
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(input) {
+        Ok(dir) => dir.filter_map(Result::ok).map(|e| e.path()).collect(),
+        Err(_) => return found,
+    };
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("aml") => found.acpi.push(name),
+            Some("kext") => add_kext(&path, &name, &mut found),
+            Some("efi") => match resources.resource_list[&name]["res_type"].as_str() {
+                Some("driver") => found.drivers.push(name),
+                Some("tool") => found.tools.push(name),
+                _ => found.unknown.push(name),
+            },
+            _ => (),
+        }
+    }
+    found
+}
+
+/// the built OUTPUT/EFI/OC folder already has resources sorted into folders
+fn scan_output(oc: &Path) -> Found {
+    let mut found = Found::default();
+    for entry in WalkDir::new(oc.join("ACPI"))
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        if entry.path().extension().and_then(|e| e.to_str()) == Some("aml") {
+            let rel = entry.path().strip_prefix(oc.join("ACPI")).unwrap();
+            found.acpi.push(rel.to_string_lossy().to_string());
+        }
+    }
+    for (folder, list) in [("Drivers", &mut found.drivers), ("Tools", &mut found.tools)] {
+        if let Ok(dir) = std::fs::read_dir(oc.join(folder)) {
+            let mut names: Vec<String> = dir
+                .filter_map(Result::ok)
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|n| n.to_lowercase().ends_with(".efi"))
+                .collect();
+            names.sort();
+            list.append(&mut names);
+        }
+    }
+    if let Ok(dir) = std::fs::read_dir(oc.join("Kexts")) {
+        let mut kexts: Vec<PathBuf> = dir.filter_map(Result::ok).map(|e| e.path()).collect();
+        kexts.sort();
+        for path in kexts {
+            if path.extension().and_then(|e| e.to_str()) == Some("kext") {
+                let name = path.file_name().unwrap().to_string_lossy().to_string();
+                add_kext(&path, &name, &mut found);
+            }
+        }
+    }
+    found
+}
+
+/// add kext at `path` along with any kexts in its Contents/PlugIns folder
+fn add_kext(path: &Path, bundle_path: &str, found: &mut Found) {
+    found
+        .kexts
+        .push((bundle_path.to_owned(), executable_path(path)));
+    if let Ok(dir) = std::fs::read_dir(path.join("Contents/PlugIns")) {
+        let mut plugins: Vec<PathBuf> = dir.filter_map(Result::ok).map(|e| e.path()).collect();
+        plugins.sort();
+        for plugin in plugins {
+            if plugin.extension().and_then(|e| e.to_str()) == Some("kext") {
+                let mut plugin_path = bundle_path.to_owned();
+                plugin_path.push_str("/Contents/PlugIns/");
+                plugin_path.push_str(&plugin.file_name().unwrap().to_string_lossy());
+                add_kext(&plugin, &plugin_path, found);
+            }
+        }
+    }
+}
+
+/// ExecutablePath of a kext from its Info.plist, empty for plist only kexts
+fn executable_path(kext: &Path) -> String {
+    let info = match Value::from_file(kext.join("Contents/Info.plist")) {
+        Ok(v) => v,
+        Err(_) => return "".to_string(),
+    };
+    let exe = info
+        .as_dictionary()
+        .and_then(|d| d.get("CFBundleExecutable"))
+        .and_then(|e| e.as_string())
+        .unwrap_or("");
+    if exe.is_empty() || !kext.join("Contents/MacOS").join(exe).exists() {
+        "".to_string()
+    } else {
+        format!("Contents/MacOS/{}", exe)
+    }
+}
@@ -0,0 +1,604 @@
+use crate::acpi;
+use crate::aml::{self, Object};
+use crate::edit::read_key;
+use crate::init::Settings;
+use crate::res::Resources;
+use crate::snapshot;
+
+use crossterm::event::KeyCode;
+use plist::Value;
+
+use std::fs;
+use std::io::{Stdout, Write};
+
+const ZERO_OP: u8 = 0x00;
+const ONE_OP: u8 = 0x01;
+const NAME_OP: u8 = 0x08;
+const BYTE_PREFIX: u8 = 0x0a;
+const WORD_PREFIX: u8 = 0x0b;
+const DWORD_PREFIX: u8 = 0x0c;
+const STRING_PREFIX: u8 = 0x0d;
+const QWORD_PREFIX: u8 = 0x0e;
+const SCOPE_OP: u8 = 0x10;
+const BUFFER_OP: u8 = 0x11;
+const PACKAGE_OP: u8 = 0x12;
+const METHOD_OP: u8 = 0x14;
+const EXTERNAL_OP: u8 = 0x15;
+const DUAL_NAME_PREFIX: u8 = 0x2e;
+const MULTI_NAME_PREFIX: u8 = 0x2f;
+const ARG2_OP: u8 = 0x6a;
+const STORE_OP: u8 = 0x70;
+const LEQUAL_OP: u8 = 0x93;
+const IF_OP: u8 = 0xa0;
+const ELSE_OP: u8 = 0xa1;
+const RETURN_OP: u8 = 0xa4;
+const DEVICE_OP: &[u8] = &[0x5b, 0x82];
+
+// ObjectType values used by External
+const INT_OBJ: u8 = 1;
+const DEVICE_OBJ: u8 = 6;
+const PROCESSOR_OBJ: u8 = 12;
+
+/// names the Intel LPC/eSPI bridge goes by, used if the dump has no device at 1F.0
+const LPC_NAMES: &[&str] = &["LPCB", "LPC0", "LPC", "SBRG", "PX40"];
+
+/// a generated table ready to be written to the INPUT folder
+struct Ssdt {
+    file: &'static str, // file name in the INPUT folder
+    aml: Vec<u8>,
+}
+
+/// generate the SSDT-EC-USBX, SSDT-PLUG, SSDT-AWAC and SSDT-PMC hotpatches using the
+/// device names found in the INPUT/ACPI_dump tables, save them in the INPUT folder
+/// and add them to ACPI > Add, asking before replacing a different file of the same name
+pub fn generate(settings: &mut Settings, resources: &mut Resources, stdout: &mut Stdout) {
+    let objects = aml::scan_dump(&resources.input_dir_path);
+    if objects.is_empty() {
+        write!(
+            stdout,
+            "\r\n\x1b[31mERROR:\x1b[0m no DSDT or SSDT tables found in the {} folder, \
+             dump them first with 'octool -a'\x1b[0K\r\n",
+            acpi::DUMP_DIR
+        )
+        .unwrap();
+        return;
+    }
+    write!(
+        stdout,
+        "\r\n\x1b[2K\x1b[4mGenerate\x1b[0m '\x1b[32me\x1b[0m' SSDT-EC-USBX  '\x1b[32mp\x1b[0m' SSDT-PLUG  \
+         '\x1b[32mw\x1b[0m' SSDT-AWAC  '\x1b[32mm\x1b[0m' SSDT-PMC  \
+         '\x1b[32mA\x1b[0m' all that the dump needs, any other key to cancel\x1b[0K\r\n\x1b[2K"
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+    let wanted: Vec<char> = match read_key().unwrap().0 {
+        KeyCode::Char('A') => vec!['e', 'p', 'w'],
+        KeyCode::Char(c) if "epwm".contains(c) => vec![c],
+        _ => return,
+    };
+
+    let mut tables = vec![];
+    for c in wanted {
+        let table = match c {
+            'e' => ec_usbx(&objects),
+            'p' => plug(&objects),
+            'w' => awac(&objects),
+            _ => pmc(&objects),
+        };
+        match table {
+            Ok(t) => tables.push(t),
+            Err(e) => write!(stdout, "\x1b[33mSkipping\x1b[0m {}\x1b[0K\r\n", e).unwrap(),
+        }
+    }
+
+    let mut written = vec![];
+    for table in tables {
+        let path = resources.input_dir_path.join(table.file);
+        let unchanged = match fs::read(&path) {
+            Ok(old) if old == table.aml => true,
+            Ok(_) => {
+                write!(
+                    stdout,
+                    "\x1b[33m{:?} already exists\x1b[0m, press '\x1b[32my\x1b[0m' to replace it, \
+                     any other key to keep it\x1b[0K\r\n",
+                    path
+                )
+                .unwrap();
+                stdout.flush().unwrap();
+                if read_key().unwrap().0 != KeyCode::Char('y') {
+                    write!(stdout, "\x1b[33mKept\x1b[0m {:?}\x1b[0K\r\n", path).unwrap();
+                    continue;
+                }
+                false
+            }
+            Err(_) => false,
+        };
+        if unchanged {
+            write!(stdout, "\x1b[32mUnchanged\x1b[0m {:?}\x1b[0K\r\n", path).unwrap();
+        } else if let Err(e) = fs::write(&path, &table.aml) {
+            write!(
+                stdout,
+                "\x1b[31mERROR:\x1b[0m could not write {:?}: {}\x1b[0K\r\n",
+                path, e
+            )
+            .unwrap();
+            continue;
+        } else {
+            written.push(table.file);
+        }
+        let comment = acpi::parse_header(&table.aml).unwrap().comment();
+        if add_entry(resources, table.file, &comment) {
+            settings.modified = true;
+            write!(
+                stdout,
+                "\x1b[7mAdded\x1b[0m ACPI->Add->{}\x1b[0K\r\n",
+                table.file
+            )
+            .unwrap();
+        }
+    }
+    if !written.is_empty() {
+        write!(
+            stdout,
+            "\x1b[32mWrote\x1b[0m {} to {:?}\x1b[0K\r\n",
+            written.join(", "),
+            resources.input_dir_path
+        )
+        .unwrap();
+    }
+    stdout.flush().unwrap();
+}
+
+/// enable the ACPI > Add entry for `file`, adding it if needed, returns true if changed
+fn add_entry(resources: &mut Resources, file: &str, comment: &str) -> bool {
+    let template = snapshot::sample_template(resources, "ACPI", "Add");
+    let add = match resources
+        .config_plist
+        .as_dictionary_mut()
+        .and_then(|d| d.get_mut("ACPI"))
+        .and_then(|a| a.as_dictionary_mut())
+        .and_then(|a| a.get_mut("Add"))
+        .and_then(|a| a.as_array_mut())
+    {
+        Some(a) => a,
+        None => return false,
+    };
+    for entry in add.iter_mut().filter_map(|e| e.as_dictionary_mut()) {
+        if entry.get("Path").and_then(|p| p.as_string()) == Some(file) {
+            let enabled = entry.insert("Enabled".to_string(), Value::Boolean(true));
+            return enabled != Some(Value::Boolean(true));
+        }
+    }
+    match template {
+        Some(Value::Dictionary(mut d)) => {
+            d.insert("Path".to_string(), Value::String(file.to_string()));
+            d.insert("Comment".to_string(), Value::String(comment.to_string()));
+            d.insert("Enabled".to_string(), Value::Boolean(true));
+            add.push(Value::Dictionary(d));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// fake EC device so AppleBusPowerController loads, plus the USBX power properties
+fn ec_usbx(objects: &[Object]) -> Result<Ssdt, String> {
+    let lpc = lpc_device(objects)?;
+    let lpc_path = lpc.path_string();
+    let mut body = external(&lpc_path, DEVICE_OBJ);
+
+    // a real EC already named EC would clash with the fake one
+    let ec_path = [lpc.path.clone(), vec!["EC__".to_string()]].concat();
+    if !objects.iter().any(|o| o.path == ec_path) {
+        let ec = device(
+            "EC",
+            [
+                name("_HID", string("ACID0001")),
+                method("_STA", 0, darwin_only(0x0f)),
+            ]
+            .concat(),
+        );
+        body.extend(scope(&lpc_path, ec));
+    }
+
+    let dsm = [
+        if_op(
+            [vec![LEQUAL_OP, ARG2_OP], integer(0)].concat(),
+            return_op(buffer(&[0x03])),
+        ),
+        return_op(package(vec![
+            string("kUSBSleepPowerSupply"),
+            integer(0x13ec),
+            string("kUSBSleepPortCurrentLimit"),
+            integer(0x0834),
+            string("kUSBWakePowerSupply"),
+            integer(0x13ec),
+            string("kUSBWakePortCurrentLimit"),
+            integer(0x0834),
+        ])),
+    ]
+    .concat();
+    let usbx = device(
+        "USBX",
+        [
+            name("_ADR", integer(0)),
+            method("_DSM", 4, dsm),
+            method("_STA", 0, darwin_only(0x0f)),
+        ]
+        .concat(),
+    );
+    body.extend(scope("\\_SB", usbx));
+    Ok(Ssdt {
+        file: "SSDT-EC-USBX.aml",
+        aml: table("SsdtEC", body),
+    })
+}
+
+/// plugin-type on the first processor object so XCPM power management is used
+fn plug(objects: &[Object]) -> Result<Ssdt, String> {
+    let cpu = objects
+        .iter()
+        .find(|o| {
+            o.kind == "Processor" || (o.kind == "Device" && o.hid.as_deref() == Some("ACPI0007"))
+        })
+        .ok_or("SSDT-PLUG, no processor objects found in the dump")?;
+    let cpu_path = cpu.path_string();
+    let obj_type = if cpu.kind == "Processor" {
+        PROCESSOR_OBJ
+    } else {
+        DEVICE_OBJ
+    };
+    let dsm = [
+        if_op(
+            osi_darwin(),
+            [
+                if_op(
+                    [vec![LEQUAL_OP, ARG2_OP], integer(0)].concat(),
+                    return_op(buffer(&[0x03])),
+                ),
+                return_op(package(vec![string("plugin-type"), integer(1)])),
+            ]
+            .concat(),
+        ),
+        return_op(buffer(&[0x00])),
+    ]
+    .concat();
+    let body = [
+        external(&cpu_path, obj_type),
+        scope(&cpu_path, method("_DSM", 4, dsm)),
+    ]
+    .concat();
+    Ok(Ssdt {
+        file: "SSDT-PLUG.aml",
+        aml: table("CpuPlug", body),
+    })
+}
+
+/// turn the legacy RTC back on with the STAS switch when the board uses AWAC
+fn awac(objects: &[Object]) -> Result<Ssdt, String> {
+    let has = |path: &[&str]| {
+        objects
+            .iter()
+            .any(|o| o.path.iter().map(|s| s.as_str()).eq(path.iter().copied()))
+    };
+    if !objects
+        .iter()
+        .any(|o| o.kind == "Device" && o.hid.as_deref() == Some("ACPI000E"))
+    {
+        return Err("SSDT-AWAC, no AWAC clock found in the dump, it is not needed".to_string());
+    }
+    if !has(&["STAS"]) {
+        return Err(
+            "SSDT-AWAC, the dump has no STAS switch, use a fake RTC such as SSDT-RTC0 instead"
+                .to_string(),
+        );
+    }
+    if has(&["_SB_", "_INI"]) {
+        return Err(
+            "SSDT-AWAC, \\_SB._INI already exists, it would clash with the generated table"
+                .to_string(),
+        );
+    }
+    let ini = if_op(
+        osi_darwin(),
+        [vec![STORE_OP, ONE_OP], name_path("STAS")].concat(),
+    );
+    let body = [
+        external("\\STAS", INT_OBJ),
+        scope("\\_SB", method("_INI", 0, ini)),
+    ]
+    .concat();
+    Ok(Ssdt {
+        file: "SSDT-AWAC.aml",
+        aml: table("AWAC", body),
+    })
+}
+
+/// PMC device that 300 series boards need for NVRAM to work
+fn pmc(objects: &[Object]) -> Result<Ssdt, String> {
+    let lpc = lpc_device(objects)?.path_string();
+    // ResourceTemplate with Memory32Fixed (ReadWrite, 0xFE000000, 0x00010000)
+    let crs = buffer(&[
+        0x86, 0x09, 0x00, 0x01, 0x00, 0x00, 0x00, 0xfe, 0x00, 0x00, 0x01, 0x00, 0x79, 0x00,
+    ]);
+    let pmcr = device(
+        "PMCR",
+        [
+            name("_HID", integer(eisa_id("APP9876") as u64)),
+            method("_STA", 0, darwin_only(0x0b)),
+            name("_CRS", crs),
+        ]
+        .concat(),
+    );
+    let body = [external(&lpc, DEVICE_OBJ), scope(&lpc, pmcr)].concat();
+    Ok(Ssdt {
+        file: "SSDT-PMC.aml",
+        aml: table("PMCR", body),
+    })
+}
+
+/// the LPC bridge, the PCI device at 1F.0
+fn lpc_device(objects: &[Object]) -> Result<&Object, String> {
+    objects
+        .iter()
+        .find(|o| {
+            o.kind == "Device"
+                && o.adr == Some(0x001f_0000)
+                && o.path.first().map(|s| s.as_str()) == Some("_SB_")
+        })
+        .or_else(|| {
+            objects.iter().find(|o| {
+                o.kind == "Device"
+                    && o.path.len() == 3
+                    && LPC_NAMES.contains(&o.path[2].trim_end_matches('_'))
+            })
+        })
+        .ok_or_else(|| "no LPC bridge found in the dump".to_string())
+}
+
+/// wrap `body` in an SSDT with the given OEM table id
+fn table(table_id: &str, body: Vec<u8>) -> Vec<u8> {
+    let mut aml = vec![];
+    aml.extend(b"SSDT");
+    aml.extend(((acpi::HEADER_SIZE + body.len()) as u32).to_le_bytes());
+    aml.push(2); // revision
+    aml.push(0); // checksum, filled in below
+    aml.extend(b"OCTOOL");
+    aml.extend(format!("{:<8}", table_id).as_bytes());
+    aml.extend(0x1000u32.to_le_bytes());
+    aml.extend(b"OCTL");
+    aml.extend(1u32.to_le_bytes());
+    aml.extend(body);
+    let sum = aml.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    aml[9] = 0u8.wrapping_sub(sum);
+    aml
+}
+
+/// body of a _STA that returns `sta` on macOS and 0 elsewhere
+fn darwin_only(sta: u64) -> Vec<u8> {
+    [
+        if_op(osi_darwin(), return_op(integer(sta))),
+        pkg(&[ELSE_OP], return_op(integer(0))),
+    ]
+    .concat()
+}
+
+fn osi_darwin() -> Vec<u8> {
+    [name_path("_OSI"), string("Darwin")].concat()
+}
+
+fn scope(path: &str, body: Vec<u8>) -> Vec<u8> {
+    pkg(&[SCOPE_OP], [name_path(path), body].concat())
+}
+
+fn device(name: &str, body: Vec<u8>) -> Vec<u8> {
+    pkg(DEVICE_OP, [name_path(name), body].concat())
+}
+
+fn method(name: &str, args: u8, body: Vec<u8>) -> Vec<u8> {
+    pkg(
+        &[METHOD_OP],
+        [name_path(name), vec![args & 0x07], body].concat(),
+    )
+}
+
+fn name(name: &str, value: Vec<u8>) -> Vec<u8> {
+    [vec![NAME_OP], name_path(name), value].concat()
+}
+
+fn external(path: &str, obj_type: u8) -> Vec<u8> {
+    [vec![EXTERNAL_OP], name_path(path), vec![obj_type, 0]].concat()
+}
+
+fn if_op(predicate: Vec<u8>, body: Vec<u8>) -> Vec<u8> {
+    pkg(&[IF_OP], [predicate, body].concat())
+}
+
+fn return_op(value: Vec<u8>) -> Vec<u8> {
+    [vec![RETURN_OP], value].concat()
+}
+
+fn buffer(bytes: &[u8]) -> Vec<u8> {
+    pkg(
+        &[BUFFER_OP],
+        [integer(bytes.len() as u64), bytes.to_vec()].concat(),
+    )
+}
+
+fn package(elements: Vec<Vec<u8>>) -> Vec<u8> {
+    pkg(
+        &[PACKAGE_OP],
+        [vec![elements.len() as u8], elements.concat()].concat(),
+    )
+}
+
+fn string(s: &str) -> Vec<u8> {
+    [vec![STRING_PREFIX], s.as_bytes().to_vec(), vec![0]].concat()
+}
+
+fn integer(i: u64) -> Vec<u8> {
+    match i {
+        0 => vec![ZERO_OP],
+        1 => vec![ONE_OP],
+        2..=0xff => vec![BYTE_PREFIX, i as u8],
+        0x100..=0xffff => [vec![WORD_PREFIX], (i as u16).to_le_bytes().to_vec()].concat(),
+        0x1_0000..=0xffff_ffff => [vec![DWORD_PREFIX], (i as u32).to_le_bytes().to_vec()].concat(),
+        _ => [vec![QWORD_PREFIX], i.to_le_bytes().to_vec()].concat(),
+    }
+}
+
+/// encode `op` followed by the PkgLength of `body` and the body
+fn pkg(op: &[u8], body: Vec<u8>) -> Vec<u8> {
+    // the length includes the PkgLength bytes themselves
+    let extra = match body.len() + 1 {
+        0..=0x3f => 0,
+        0x40..=0xffe => 1,
+        0xfff..=0xf_fffd => 2,
+        _ => 3,
+    };
+    let len = body.len() + 1 + extra;
+    let mut out = op.to_vec();
+    if extra == 0 {
+        out.push(len as u8);
+    } else {
+        out.push(((extra as u8) << 6) | (len & 0x0f) as u8);
+        for i in 0..extra {
+            out.push((len >> (4 + 8 * i)) as u8);
+        }
+    }
+    out.extend(body);
+    out
+}
+
+/// encode a path such as \_SB.PCI0.LPCB or a single name such as EC as a NameString
+fn name_path(path: &str) -> Vec<u8> {
+    let mut out = vec![];
+    let mut rest = path;
+    if let Some(r) = rest.strip_prefix('\\') {
+        out.push(b'\\');
+        rest = r;
+    }
+    let segs: Vec<Vec<u8>> = rest
+        .split('.')
+        .map(|s| format!("{:_<4}", s).into_bytes())
+        .collect();
+    match segs.len() {
+        1 => (),
+        2 => out.push(DUAL_NAME_PREFIX),
+        n => out.extend([MULTI_NAME_PREFIX, n as u8]),
+    }
+    out.extend(segs.concat());
+    out
+}
+
+/// compress an id such as PNP0C09 into its EISA id integer
+fn eisa_id(id: &str) -> u32 {
+    let b = id.as_bytes();
+    let vendor = ((b[0] as u32 - 0x40) << 10) | ((b[1] as u32 - 0x40) << 5) | (b[2] as u32 - 0x40);
+    let product = u16::from_str_radix(&id[3..7], 16).unwrap_or(0) as u32;
+    let bytes = [
+        (vendor >> 8) as u8,
+        vendor as u8,
+        (product >> 8) as u8,
+        product as u8,
+    ];
+    u32::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(path: &str, kind: &'static str) -> Object {
+        Object {
+            path: path.split('.').map(|s| format!("{:_<4}", s)).collect(),
+            kind,
+            ..Default::default()
+        }
+    }
+
+    fn lpc() -> Object {
+        Object {
+            adr: Some(0x001f_0000),
+            ..object("_SB.PCI0.LPCB", "Device")
+        }
+    }
+
+    #[test]
+    fn encodes_pkg_length() {
+        assert_eq!(pkg(&[SCOPE_OP], vec![0; 2]), vec![SCOPE_OP, 3, 0, 0]);
+        // 0x40 bytes of body need a second length byte, 0x42 in all
+        let long = pkg(&[SCOPE_OP], vec![0; 0x40]);
+        assert_eq!(long[..3], [SCOPE_OP, 0x42, 0x04]);
+        assert_eq!(long.len(), 0x43);
+        let longer = pkg(DEVICE_OP, vec![0; 0x1000]);
+        assert_eq!(longer[..5], [0x5b, 0x82, 0x83, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn encodes_names_and_data() {
+        assert_eq!(name_path("EC"), b"EC__");
+        assert_eq!(name_path("\\STAS"), b"\\STAS");
+        assert_eq!(name_path("_SB.PCI0"), b"\x2e_SB_PCI0");
+        assert_eq!(name_path("\\_SB.PCI0.LPCB"), b"\\\x2f\x03_SB_PCI0LPCB");
+        assert_eq!(integer(0), vec![ZERO_OP]);
+        assert_eq!(integer(1), vec![ONE_OP]);
+        assert_eq!(integer(0x13ec), vec![WORD_PREFIX, 0xec, 0x13]);
+        assert_eq!(integer(0x1_0000), vec![DWORD_PREFIX, 0, 0, 1, 0]);
+        assert_eq!(integer(1 << 32)[0], QWORD_PREFIX);
+        assert_eq!(string("EC"), b"\x0dEC\0");
+        assert_eq!(buffer(&[3]), vec![BUFFER_OP, 3, ONE_OP, 3]);
+        assert_eq!(eisa_id("PNP0C09"), 0x090c_d041);
+    }
+
+    #[test]
+    fn tables_have_valid_headers() {
+        let aml = table("CpuPlug", vec![0xa3; 5]);
+        let header = acpi::parse_header(&aml).unwrap();
+        assert_eq!(header.signature, "SSDT");
+        assert_eq!(header.length as usize, acpi::HEADER_SIZE + 5);
+        assert_eq!(header.oem_table_id, "CpuPlug");
+        assert!(header.checksum_valid());
+    }
+
+    #[test]
+    fn generated_tables_scan_back() {
+        let cpu = object("_PR.CPU0", "Processor");
+        let objects = [cpu, lpc()];
+        let dir = std::env::temp_dir().join(format!("octool_ssdt_{}", std::process::id()));
+        fs::create_dir_all(dir.join(acpi::DUMP_DIR)).unwrap();
+        for ssdt in [ec_usbx(&objects), plug(&objects), pmc(&objects)] {
+            let ssdt = ssdt.unwrap();
+            fs::write(dir.join(acpi::DUMP_DIR).join(ssdt.file), ssdt.aml).unwrap();
+        }
+        let scanned = aml::scan_dump(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let find = |path: &str| scanned.iter().find(|o| o.path_string() == path);
+        assert_eq!(
+            find("\\_SB.PCI0.LPCB.EC").unwrap().hid.as_deref(),
+            Some("ACID0001")
+        );
+        assert_eq!(
+            find("\\_SB.PCI0.LPCB.PMCR").unwrap().hid.as_deref(),
+            Some("APP9876")
+        );
+        assert_eq!(find("\\_SB.USBX").unwrap().adr, Some(0));
+        assert_eq!(find("\\_PR.CPU0._DSM").unwrap().kind, "Method");
+    }
+
+    #[test]
+    fn skips_what_is_not_needed() {
+        let ec = object("_SB.PCI0.LPCB.EC", "Device");
+        let ssdt = ec_usbx(&[lpc(), ec]).unwrap();
+        assert!(!ssdt.aml.windows(4).any(|w| w == b"ACID"));
+        assert!(ec_usbx(&[]).is_err());
+        assert!(plug(&[lpc()]).is_err());
+        assert!(awac(&[lpc()]).is_err());
+        let clock = Object {
+            hid: Some("ACPI000E".to_string()),
+            ..object("_SB.AWAC", "Device")
+        };
+        let stas = object("STAS", "Name");
+        assert!(awac(&[clock, stas]).is_ok());
+    }
+}